@@ -0,0 +1,57 @@
+//! Ticks per second of the simulation dispatcher for universes of increasing size.
+//!
+//! Run with `cargo bench`. The throughput reported by criterion is in ticks per second.
+
+use amethyst::ecs::{Dispatcher, World, WorldExt};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use traders::generator::{self, GeneratorConfig};
+use traders::resources::*;
+use traders::simulation;
+
+/// Ticks simulated before measuring, such that the navigation graphs have been built and ships are underway.
+const WARMUP_TICKS: u32 = 100;
+
+fn setup(config: &GeneratorConfig) -> (World, Dispatcher<'static, 'static>) {
+    let mut world = World::new();
+    let mut dispatcher = simulation::dispatcher(false).build();
+    dispatcher.setup(&mut world);
+    simulation::register_components(&mut world);
+
+    world.insert(CurrentTime::default());
+    world.insert(SimRng::seed_from_u64(0));
+
+    generator::generate(0, config).create(&mut world);
+
+    for _ in 0..WARMUP_TICKS {
+        simulation::tick(&mut dispatcher, &mut world);
+    }
+
+    (world, dispatcher)
+}
+
+fn ticks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ticks");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(1));
+
+    for &(stations, ships_per_station) in &[(20, 2), (100, 10), (300, 10)] {
+        let config = GeneratorConfig {
+            sectors: 10,
+            stations,
+            ships_per_station,
+            ..GeneratorConfig::default()
+        };
+        let (mut world, mut dispatcher) = setup(&config);
+
+        let id = format!("{}x{}", stations, stations * ships_per_station);
+        group.bench_function(BenchmarkId::from_parameter(id), |b| {
+            b.iter(|| simulation::tick(&mut dispatcher, &mut world))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, ticks);
+criterion_main!(benches);
@@ -0,0 +1,368 @@
+use amethyst::ecs::{join::Join, Entities, ReadStorage, World, WorldExt};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::commands::{Command, PendingCommands};
+use crate::components::*;
+use crate::factions::Factions;
+use crate::replay::ReplayState;
+use crate::resources::{CurrentTime, ResumeSpeed, SimulationSpeed};
+
+/// Request to the control API, sent as a single line of JSON such as `{"type": "fly_to", "ship": 12, "target": 3}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Time,
+    /// Ships and stations, optionally only those in the given sector.
+    Entities {
+        sector: Option<u32>,
+    },
+    /// Stock and production of every station.
+    Markets,
+    FlyTo {
+        ship: u32,
+        target: u32,
+    },
+    Pause,
+    /// Change the speed to `paused`, `max`, or the number of ticks per tick of real time.
+    Speed {
+        speed: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Time { tick: u32 },
+    Entities { entities: Vec<EntityView> },
+    Markets { stations: Vec<MarketView> },
+    Ok,
+    Error { message: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Station,
+    Trader,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BehaviourView {
+    Idle,
+    FlyTo { target: u32 },
+    FollowPath { target: u32 },
+    Jump { gate: u32, target: u32 },
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntityView {
+    pub id: u32,
+    pub name: Option<String>,
+    pub kind: Kind,
+    pub sector: u32,
+    pub position: (f32, f32),
+    pub velocity: Option<(f32, f32)>,
+    pub behaviour: Option<BehaviourView>,
+    pub cargo: Vec<(CargoType, i32)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarketView {
+    pub id: u32,
+    pub name: Option<String>,
+    pub sector: u32,
+    pub owner: Option<String>,
+    pub recipe: Option<&'static str>,
+    /// Ticks spent on the current batch, if one is being fabricated.
+    pub progress: Option<u32>,
+    pub cargo: Vec<(CargoType, i32)>,
+}
+
+type Pending = (Request, Sender<Response>);
+
+/// Local server through which scripts and dashboards query and control the simulation.
+///
+/// Connections are served on their own threads, while requests are answered on the simulation thread by `process`.
+/// Orders are queued as commands, such that they are recorded like those of the player.
+pub struct ApiServer {
+    requests: Mutex<Receiver<Pending>>,
+}
+
+impl fmt::Debug for ApiServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiServer")
+    }
+}
+
+impl ApiServer {
+    /// Serve on the given address, which must be a loopback address as the API has no authentication.
+    pub fn start(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        if let Some(addr) = addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is not a loopback address, while the control API has no authentication",
+                    addr
+                ),
+            ));
+        }
+
+        let listener = TcpListener::bind(&addrs[..])?;
+        info!("Control API listening on {}.", listener.local_addr()?);
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = serve(stream, sender) {
+                                warn!("API connection failed: {}.", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Could not accept API connection: {}.", e),
+                }
+            }
+        });
+
+        Ok(Self {
+            requests: Mutex::new(receiver),
+        })
+    }
+
+    /// Answer all requests received so far. To be called between ticks.
+    pub fn process(&self, world: &mut World) {
+        let requests = self
+            .requests
+            .lock()
+            .expect("only used by the simulation thread");
+        for (request, reply) in requests.try_iter() {
+            // The connection may have been closed in the meantime.
+            let _ = reply.send(respond(world, request));
+        }
+    }
+}
+
+/// Pass the requests of a connection to the simulation, one line at a time, and write back the responses.
+fn serve(stream: TcpStream, sender: Sender<Pending>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let (reply, response) = channel();
+                if sender.send((request, reply)).is_err() {
+                    break;
+                }
+                response.recv().unwrap_or_else(|_| Response::Error {
+                    message: "simulation stopped".to_owned(),
+                })
+            }
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        };
+
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+fn respond(world: &mut World, request: Request) -> Response {
+    match request {
+        Request::Time => Response::Time {
+            tick: *world.read_resource::<CurrentTime>().0,
+        },
+        Request::Entities { sector } => Response::Entities {
+            entities: entities(world, sector.map(Sector)),
+        },
+        Request::Markets => Response::Markets {
+            stations: markets(world),
+        },
+        Request::FlyTo { ship, target } => {
+            if world.has_value::<ReplayState>() {
+                return error("ships cannot be ordered while replaying".to_owned());
+            }
+
+            let (entities, trader, station): (Entities, ReadStorage<Trader>, ReadStorage<Station>) =
+                world.system_data();
+            let (ship_entity, target_entity) = (entities.entity(ship), entities.entity(target));
+
+            if !entities.is_alive(ship_entity) || !trader.contains(ship_entity) {
+                return error(format!("{} is not a ship", ship));
+            }
+            if !entities.is_alive(target_entity) || !station.contains(target_entity) {
+                return error(format!("{} is not a station", target));
+            }
+
+            world
+                .write_resource::<PendingCommands>()
+                .0
+                .push(Command::FlyTo { ship, target });
+            Response::Ok
+        }
+        Request::Pause => set_speed(world, SimulationSpeed::Paused),
+        Request::Speed { speed } => match speed.parse() {
+            Ok(speed) => set_speed(world, speed),
+            Err(e) => error(e),
+        },
+    }
+}
+
+fn error(message: String) -> Response {
+    Response::Error { message }
+}
+
+/// Change the speed the same way the bindings of the game do, such that unpausing there resumes the right speed.
+fn set_speed(world: &mut World, speed: SimulationSpeed) -> Response {
+    match (
+        world.try_fetch_mut::<SimulationSpeed>(),
+        world.try_fetch_mut::<ResumeSpeed>(),
+    ) {
+        (Some(mut current), Some(mut resume)) => {
+            current.change(speed, &mut resume);
+            Response::Ok
+        }
+        _ => error("the speed can only be changed in the game".to_owned()),
+    }
+}
+
+fn cargo_view(cargo: Option<&Cargo>) -> Vec<(CargoType, i32)> {
+    cargo.map_or_else(Vec::new, |cargo| {
+        cargo
+            .iter()
+            .filter(|&(_, amount)| amount != CargoUnits(0))
+            .map(|(t, amount)| (t, *amount))
+            .collect()
+    })
+}
+
+fn entities(world: &World, only: Option<Sector>) -> Vec<EntityView> {
+    let (entities, name, station, trader, sector, pos, vel, behaviour, cargo): (
+        Entities,
+        ReadStorage<Name>,
+        ReadStorage<Station>,
+        ReadStorage<Trader>,
+        ReadStorage<Sector>,
+        ReadStorage<Position>,
+        ReadStorage<Velocity>,
+        ReadStorage<ShipBehaviour>,
+        ReadStorage<Cargo>,
+    ) = world.system_data();
+
+    (
+        &entities,
+        (&name).maybe(),
+        (&station).maybe(),
+        (&trader).maybe(),
+        &sector,
+        &pos,
+        (&vel).maybe(),
+        (&behaviour).maybe(),
+        (&cargo).maybe(),
+    )
+        .join()
+        .filter_map(
+            |(e, name, station, trader, sector, pos, vel, behaviour, cargo)| {
+                if only.map_or(false, |only| only != *sector) {
+                    return None;
+                }
+
+                let kind = match (station, trader) {
+                    (Some(_), _) => Kind::Station,
+                    (_, Some(_)) => Kind::Trader,
+                    _ => return None,
+                };
+
+                Some(EntityView {
+                    id: e.id(),
+                    name: name.map(|name| name.0.clone()),
+                    kind,
+                    sector: sector.0,
+                    position: (pos.x, pos.y),
+                    velocity: vel.map(|vel| (vel.vector.x, vel.vector.y)),
+                    behaviour: behaviour.map(|behaviour| match behaviour {
+                        ShipBehaviour::Idle => BehaviourView::Idle,
+                        ShipBehaviour::FlyTo(target) => BehaviourView::FlyTo {
+                            target: target.id(),
+                        },
+                        ShipBehaviour::FollowPath(target, _) => BehaviourView::FollowPath {
+                            target: target.id(),
+                        },
+                        ShipBehaviour::Jump(gate, target) => BehaviourView::Jump {
+                            gate: gate.id(),
+                            target: target.id(),
+                        },
+                    }),
+                    cargo: cargo_view(cargo),
+                })
+            },
+        )
+        .collect()
+}
+
+fn markets(world: &World) -> Vec<MarketView> {
+    let (entities, name, station, sector, owner, module, cargo): (
+        Entities,
+        ReadStorage<Name>,
+        ReadStorage<Station>,
+        ReadStorage<Sector>,
+        ReadStorage<Faction>,
+        ReadStorage<FabricationModule>,
+        ReadStorage<Cargo>,
+    ) = world.system_data();
+    let factions = world.read_resource::<Factions>();
+
+    (
+        &entities,
+        (&name).maybe(),
+        &station,
+        &sector,
+        (&owner).maybe(),
+        (&module).maybe(),
+        (&cargo).maybe(),
+    )
+        .join()
+        .map(|(e, name, _, sector, owner, module, cargo)| MarketView {
+            id: e.id(),
+            name: name.map(|name| name.0.clone()),
+            sector: sector.0,
+            owner: owner
+                .and_then(|&owner| factions.get(owner))
+                .map(|info| info.name.clone()),
+            recipe: module.map(|module| module.recipe.name),
+            progress: module.and_then(|module| module.progress).map(|t| *t),
+            cargo: cargo_view(cargo),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_loopback_addresses_are_served() {
+        let err = ApiServer::start("0.0.0.0:0").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(ApiServer::start("127.0.0.1:0").is_ok());
+    }
+}
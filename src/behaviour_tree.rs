@@ -0,0 +1,252 @@
+use amethyst::ecs::{world::EntitiesRes, Entity};
+use rand::seq::IteratorRandom;
+use std::collections::HashMap;
+
+use crate::components::*;
+use crate::resources::SimRng;
+
+/// Outcome of ticking a node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    /// Not yet done, to be ticked again next tick.
+    Running,
+}
+
+/// Node of a behaviour tree.
+///
+/// Trees hold no state of their own: they are ticked from the root every tick, and nodes which need to remember
+/// anything keep it on the blackboard of the ship.
+#[derive(Debug, Clone)]
+pub enum Node {
+    /// Tick the children in order, until one of them does not succeed.
+    Sequence(Vec<Node>),
+    /// Tick the children in order, until one of them does not fail.
+    Selector(Vec<Node>),
+    Condition(Condition),
+    Task(Task),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Condition {
+    HasTarget,
+    CargoFull,
+    CargoEmpty,
+}
+
+/// Leaf doing the actual work, usually by setting the `ShipBehaviour` carried out by the other systems.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Task {
+    /// Choose any station as target.
+    PickRandomStation,
+    /// Fly to the target, succeeding on arrival. The target is forgotten once the flight ends.
+    FlyToTarget,
+    /// Remain where the ship is for the given time.
+    Wait(Time),
+}
+
+/// Everything a node may look at or change while ticking a single ship.
+pub struct Context<'c> {
+    pub entities: &'c EntitiesRes,
+    pub time: Time,
+    pub rng: &'c mut SimRng,
+    /// All stations, in order of their entity.
+    pub stations: &'c [Entity],
+    pub blackboard: &'c mut Blackboard,
+    pub behaviour: &'c mut ShipBehaviour,
+    pub cargo: Option<&'c Cargo>,
+}
+
+impl Node {
+    pub fn tick(&self, ctx: &mut Context) -> Status {
+        match self {
+            Node::Sequence(children) => children
+                .iter()
+                .map(|child| child.tick(ctx))
+                .find(|&status| status != Status::Success)
+                .unwrap_or(Status::Success),
+            Node::Selector(children) => children
+                .iter()
+                .map(|child| child.tick(ctx))
+                .find(|&status| status != Status::Failure)
+                .unwrap_or(Status::Failure),
+            Node::Condition(condition) => {
+                if condition.holds(ctx) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::Task(task) => task.tick(ctx),
+        }
+    }
+}
+
+impl Condition {
+    fn holds(self, ctx: &Context) -> bool {
+        match self {
+            Condition::HasTarget => ctx
+                .blackboard
+                .entity(Blackboard::TARGET)
+                .map_or(false, |target| ctx.entities.is_alive(target)),
+            Condition::CargoFull => ctx.cargo.map_or(false, Cargo::is_full),
+            Condition::CargoEmpty => ctx.cargo.map_or(true, |cargo| {
+                cargo.iter().all(|(_, amount)| amount == CargoUnits(0))
+            }),
+        }
+    }
+}
+
+impl Task {
+    fn tick(self, ctx: &mut Context) -> Status {
+        match self {
+            Task::PickRandomStation => match ctx.stations.iter().choose(&mut ctx.rng.0) {
+                Some(&station) => {
+                    ctx.blackboard
+                        .set(Blackboard::TARGET, Value::Entity(station));
+                    Status::Success
+                }
+                None => Status::Failure,
+            },
+            Task::FlyToTarget => {
+                let target = match ctx.blackboard.entity(Blackboard::TARGET) {
+                    Some(target) if ctx.entities.is_alive(target) => target,
+                    _ => {
+                        ctx.blackboard.remove(Blackboard::TARGET);
+                        ctx.blackboard.remove(Blackboard::UNDERWAY);
+                        return Status::Failure;
+                    }
+                };
+
+                if ctx.blackboard.entity(Blackboard::UNDERWAY) != Some(target) {
+                    *ctx.behaviour = ShipBehaviour::FlyTo(target);
+                    ctx.blackboard
+                        .set(Blackboard::UNDERWAY, Value::Entity(target));
+                    return Status::Running;
+                }
+
+                // The movement systems turn the ship idle once it arrives, or finds no way to the target.
+                match ctx.behaviour {
+                    ShipBehaviour::Idle => {
+                        ctx.blackboard.remove(Blackboard::TARGET);
+                        ctx.blackboard.remove(Blackboard::UNDERWAY);
+                        Status::Success
+                    }
+                    _ => Status::Running,
+                }
+            }
+            Task::Wait(duration) => match ctx.blackboard.time(Blackboard::WAIT_UNTIL) {
+                Some(until) if ctx.time >= until => {
+                    ctx.blackboard.remove(Blackboard::WAIT_UNTIL);
+                    Status::Success
+                }
+                Some(_) => Status::Running,
+                None => {
+                    ctx.blackboard
+                        .set(Blackboard::WAIT_UNTIL, Value::Time(ctx.time + duration));
+                    Status::Running
+                }
+            },
+        }
+    }
+}
+
+/// Tree of the traders: fly to a random station, then choose another.
+pub fn trader() -> Node {
+    Node::Sequence(vec![
+        Node::Selector(vec![
+            Node::Condition(Condition::HasTarget),
+            Node::Task(Task::PickRandomStation),
+        ]),
+        Node::Task(Task::FlyToTarget),
+    ])
+}
+
+/// Behaviour trees ships may be steered by, by name.
+#[derive(Debug, Clone)]
+pub struct BehaviourTrees {
+    trees: HashMap<String, Node>,
+}
+
+impl Default for BehaviourTrees {
+    fn default() -> Self {
+        let mut trees = HashMap::new();
+        trees.insert("trader".to_owned(), trader());
+        Self { trees }
+    }
+}
+
+impl BehaviourTrees {
+    pub fn insert(&mut self, name: String, tree: Node) {
+        self.trees.insert(name, tree);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.trees.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::{join::Join, Builder, World, WorldExt};
+
+    fn tick(
+        tree: &Node,
+        world: &World,
+        brain: &mut Brain,
+        behaviour: &mut ShipBehaviour,
+    ) -> Status {
+        let entities = world.entities();
+        let stations: Vec<Entity> = (&entities).join().collect();
+        let mut rng = SimRng::seed_from_u64(0);
+        let mut ctx = Context {
+            entities: &entities,
+            time: Time(0),
+            rng: &mut rng,
+            stations: &stations,
+            blackboard: &mut brain.blackboard,
+            behaviour,
+            cargo: None,
+        };
+        tree.tick(&mut ctx)
+    }
+
+    #[test]
+    fn sequence_stops_at_first_failure() {
+        let world = World::new();
+        let tree = Node::Sequence(vec![
+            Node::Condition(Condition::CargoEmpty),
+            Node::Condition(Condition::HasTarget),
+            Node::Task(Task::Wait(Time(5))),
+        ]);
+        let mut brain = Brain::new("test");
+
+        let status = tick(&tree, &world, &mut brain, &mut ShipBehaviour::Idle);
+
+        assert_eq!(status, Status::Failure);
+        assert_eq!(brain.blackboard.get(Blackboard::WAIT_UNTIL), None);
+    }
+
+    #[test]
+    fn trader_flies_to_station_until_idle() {
+        let mut world = World::new();
+        let station = world.create_entity().build();
+        let mut brain = Brain::new("trader");
+        let mut behaviour = ShipBehaviour::Idle;
+
+        let status = tick(&trader(), &world, &mut brain, &mut behaviour);
+        assert_eq!(status, Status::Running);
+        assert!(matches!(behaviour, ShipBehaviour::FlyTo(target) if target == station));
+
+        // Still on its way.
+        let status = tick(&trader(), &world, &mut brain, &mut behaviour);
+        assert_eq!(status, Status::Running);
+
+        behaviour = ShipBehaviour::Idle;
+        let status = tick(&trader(), &world, &mut brain, &mut behaviour);
+        assert_eq!(status, Status::Success);
+        assert_eq!(brain.blackboard.get(Blackboard::TARGET), None);
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Order issued by the player. Commands are the only way in which the player affects the simulation, such that
+/// recording them suffices to reproduce a session.
+///
+/// Entities are referred to by id, which is stable between runs of the same scenario and seed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    FlyTo { ship: u32, target: u32 },
+}
+
+/// Commands to apply at the start of the next tick.
+#[derive(Debug, Default)]
+pub struct PendingCommands(pub Vec<Command>);
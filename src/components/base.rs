@@ -3,11 +3,12 @@ use amethyst::{
     ecs::{Component, DenseVecStorage, VecStorage},
 };
 use derive_more::{Add, AddAssign, Deref, DerefMut, Mul, Sub, SubAssign};
+use serde::{Deserialize, Serialize};
 
 pub type Point2 = math::geometry::Point2<f32>;
 pub type Translation2 = math::geometry::Translation2<f32>;
 
-// Each time unit is a frame. (1/50th of second)
+// Each time unit is a simulation tick. (1/50th of second, see simulation::TICK)
 #[derive(
     Deref,
     DerefMut,
@@ -22,11 +23,13 @@ pub type Translation2 = math::geometry::Translation2<f32>;
     Sub,
     AddAssign,
     SubAssign,
+    Serialize,
+    Deserialize,
 )]
 pub struct Time(pub u32);
 
 // Note: arithmatics directly on positions do not make sense. Hence first deref.
-#[derive(Deref, DerefMut, Clone, Copy, Debug)]
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Position(Point2);
 
 impl Component for Position {
@@ -39,7 +42,7 @@ impl Position {
     }
 }
 
-#[derive(Deref, DerefMut, Clone, Copy, Debug)]
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Velocity(Translation2);
 
 impl Component for Velocity {
@@ -58,7 +61,7 @@ impl Velocity {
     }
 }
 
-#[derive(Deref, DerefMut, Clone, Copy, Debug)]
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Hitbox(Translation2);
 
 impl Component for Hitbox {
@@ -77,7 +80,7 @@ impl Hitbox {
     }
 }
 
-#[derive(Deref, DerefMut, Clone, Copy, Debug, Default, Sub, Mul, Add)]
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Default, Sub, Mul, Add, Serialize, Deserialize)]
 pub struct Angle(f32);
 
 impl Component for Angle {
@@ -90,7 +93,7 @@ impl Angle {
     }
 }
 
-#[derive(Deref, DerefMut, Clone, Copy, Debug, Default, Sub, Mul, Add)]
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Default, Sub, Mul, Add, Serialize, Deserialize)]
 pub struct AngularMomentum(f32);
 
 impl Component for AngularMomentum {
@@ -102,3 +105,17 @@ impl AngularMomentum {
         Self(inner)
     }
 }
+
+/// Area around a static entity which ships navigate around, given as a radius.
+#[derive(Deref, DerefMut, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Obstacle(f32);
+
+impl Component for Obstacle {
+    type Storage = VecStorage<Self>;
+}
+
+impl Obstacle {
+    pub fn new(radius: f32) -> Self {
+        Self(radius)
+    }
+}
@@ -0,0 +1,90 @@
+use amethyst::ecs::{Component, Entity, VecStorage};
+use std::collections::BTreeMap;
+
+use super::Time;
+
+/// Entry on a blackboard.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Value {
+    Entity(Entity),
+    Time(Time),
+}
+
+/// Memory of a single ship, through which the nodes of its behaviour tree pass on what they found out or started.
+#[derive(Debug, Clone, Default)]
+pub struct Blackboard {
+    values: BTreeMap<String, Value>,
+}
+
+impl Blackboard {
+    /// Where the ship is meant to go.
+    pub const TARGET: &'static str = "target";
+    /// Target the ship has been sent towards, and has not yet reached.
+    pub const UNDERWAY: &'static str = "underway";
+    /// Until when the ship waits.
+    pub const WAIT_UNTIL: &'static str = "wait_until";
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.values.get(key).copied()
+    }
+
+    pub fn entity(&self, key: &str) -> Option<Entity> {
+        match self.get(key) {
+            Some(Value::Entity(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn time(&self, key: &str) -> Option<Time> {
+        match self.get(key) {
+            Some(Value::Time(t)) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.values.insert(key.to_owned(), value);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Value)> + '_ {
+        self.values
+            .iter()
+            .map(|(key, &value)| (key.as_str(), value))
+    }
+
+    /// Note that the ship was sent to the target from outside its tree, such that the tree waits for it to arrive
+    /// instead of choosing a target of its own.
+    pub fn sent_to(&mut self, target: Entity) {
+        self.clear();
+        self.set(Self::TARGET, Value::Entity(target));
+        self.set(Self::UNDERWAY, Value::Entity(target));
+    }
+}
+
+/// Behaviour tree steering a ship, by its name in `behaviour_tree::BehaviourTrees`, along with the ship's blackboard.
+#[derive(Debug, Clone)]
+pub struct Brain {
+    pub tree: String,
+    pub blackboard: Blackboard,
+}
+
+impl Brain {
+    pub fn new(tree: &str) -> Self {
+        Self {
+            tree: tree.to_owned(),
+            blackboard: Blackboard::default(),
+        }
+    }
+}
+
+impl Component for Brain {
+    type Storage = VecStorage<Self>;
+}
@@ -1,13 +1,28 @@
 use amethyst::ecs::{Component, VecStorage};
 use derive_more::{Add, Deref, DerefMut, Sub};
 use enum_map::{Enum, EnumMap};
+use serde::{Deserialize, Serialize};
 
 use super::*;
 
-#[derive(Default, Deref, DerefMut, Clone, Copy, Debug, Add, Sub, PartialEq, Eq, PartialOrd)]
+#[derive(
+    Default,
+    Deref,
+    DerefMut,
+    Clone,
+    Copy,
+    Debug,
+    Add,
+    Sub,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
 pub struct CargoUnits(pub i32);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Enum)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Enum, Serialize, Deserialize)]
 pub enum CargoType {
     CarbonOre,
     MetalOre,
@@ -28,7 +43,7 @@ pub enum CargoType {
     Hullplating,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cargo {
     inner: EnumMap<CargoType, CargoUnits>,
     cache_total: CargoUnits,
@@ -54,10 +69,23 @@ impl Cargo {
         }
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (CargoType, CargoUnits)> + '_ {
+        self.inner.iter().map(|(t, &amount)| (t, amount))
+    }
+
     pub fn is_full(&self) -> bool {
         self.cache_total == self.capacity
     }
 
+    /// Change the capacity, unless the cargo would no longer fit.
+    pub fn set_capacity(&mut self, capacity: CargoUnits) -> Result<(), CargoError> {
+        if self.cache_total > capacity {
+            return Err(CargoError::OverCapacity);
+        }
+        self.capacity = capacity;
+        Ok(())
+    }
+
     /// Adapt Cargo to possess more or less of a given CargoType.
     ///
     /// Will yield CargoError when inventory or capacity is not sufficient.
@@ -97,6 +125,8 @@ impl Cargo {
 
 #[derive(Clone, Debug)]
 pub struct FabricationRecipe {
+    /// Unique name, by which the recipe is referred to in saved games.
+    pub name: &'static str,
     pub duration: Time,
     pub ingredients: &'static [(CargoType, CargoUnits)],
     pub products: &'static [(CargoType, CargoUnits)],
@@ -0,0 +1,118 @@
+use amethyst::ecs::{
+    storage::GenericReadStorage, Builder, Component, Entity, VecStorage, World, WorldExt,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CelestialBody {
+    Star,
+    Planet,
+}
+
+impl Component for CelestialBody {
+    type Storage = VecStorage<Self>;
+}
+
+/// Region of asteroids from which ore can be mined. Ships fly straight through it.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct AsteroidField {
+    pub ore: CargoType,
+    pub radius: f32,
+}
+
+impl Component for AsteroidField {
+    type Storage = VecStorage<Self>;
+}
+
+/// Circular orbit around another entity, fully determined by the current time.
+#[derive(Debug, Copy, Clone)]
+pub struct Orbit {
+    pub center: Entity,
+    pub radius: f32,
+    pub period: Time,
+    /// Angle in radians at time zero.
+    pub phase: f32,
+}
+
+impl Component for Orbit {
+    type Storage = VecStorage<Self>;
+}
+
+impl Orbit {
+    /// Offset relative to the center at the given time.
+    pub fn offset(&self, time: Time) -> Translation2 {
+        let angle = self.phase + 2. * PI * (*time % *self.period) as f32 / *self.period as f32;
+        Translation2::new(self.radius * angle.cos(), self.radius * angle.sin())
+    }
+}
+
+/// Position of an entity at the given time, following its chain of orbits up to a fixed body.
+pub fn position_at(
+    entity: Entity,
+    time: Time,
+    orbit: &impl GenericReadStorage<Component = Orbit>,
+    pos: &impl GenericReadStorage<Component = Position>,
+) -> Option<Point2> {
+    match orbit.get(entity) {
+        Some(o) => {
+            position_at(o.center, time, orbit, pos).map(|c| o.offset(time).transform_point(&c))
+        }
+        None => pos.get(entity).map(|p| **p),
+    }
+}
+
+pub fn create_star(world: &mut World, sector: Sector, pos: Position, radius: f32) -> Entity {
+    create_celestial_body(world, CelestialBody::Star, sector, pos, None, radius)
+}
+
+pub fn create_planet(world: &mut World, sector: Sector, orbit: Orbit, radius: f32) -> Entity {
+    let pos = Position::new(Point2::new(0., 0.));
+    create_celestial_body(
+        world,
+        CelestialBody::Planet,
+        sector,
+        pos,
+        Some(orbit),
+        radius,
+    )
+}
+
+pub fn create_asteroid_field(
+    world: &mut World,
+    sector: Sector,
+    pos: Position,
+    field: AsteroidField,
+) -> Entity {
+    world
+        .create_entity()
+        .with(field)
+        .with(sector)
+        .with(pos)
+        .build()
+}
+
+pub fn create_celestial_body(
+    world: &mut World,
+    body: CelestialBody,
+    sector: Sector,
+    pos: Position,
+    orbit: Option<Orbit>,
+    radius: f32,
+) -> Entity {
+    let mut res = world
+        .create_entity()
+        .with(body)
+        .with(sector)
+        .with(pos)
+        .with(Obstacle::new(radius))
+        .with(Hitbox::new(Translation2::new(radius * 2., radius * 2.)));
+
+    if let Some(orbit) = orbit {
+        res = res.with(orbit);
+    }
+
+    res.build()
+}
@@ -0,0 +1,31 @@
+use amethyst::ecs::{Component, VecStorage};
+use derive_more::{Add, AddAssign, Deref, DerefMut, Sub, SubAssign};
+use serde::{Deserialize, Serialize};
+
+/// Party owning an entity, see `factions::Factions`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Faction(pub u32);
+
+impl Component for Faction {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(
+    Deref,
+    DerefMut,
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Add,
+    Sub,
+    AddAssign,
+    SubAssign,
+    Serialize,
+    Deserialize,
+)]
+pub struct Credits(pub i64);
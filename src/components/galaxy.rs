@@ -0,0 +1,54 @@
+use amethyst::ecs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+pub const ARENA_HEIGHT: f32 = 1000.0;
+pub const ARENA_WIDTH: f32 = 1000.0;
+
+/// Star system an entity resides in. Each sector spans its own arena.
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Sector(pub u32);
+
+impl Component for Sector {
+    type Storage = VecStorage<Self>;
+}
+
+/// Gate through which ships jump to its paired gate in another sector.
+#[derive(Debug, Copy, Clone)]
+pub struct JumpGate {
+    pub destination: Entity,
+}
+
+impl Component for JumpGate {
+    type Storage = VecStorage<Self>;
+}
+
+/// Create a pair of jump gates linking two sectors to each other.
+pub fn create_jump_gates(
+    world: &mut World,
+    (sector_a, pos_a): (Sector, Position),
+    (sector_b, pos_b): (Sector, Position),
+) -> (Entity, Entity) {
+    let a = create_jump_gate(world, sector_a, pos_a);
+    let b = create_jump_gate(world, sector_b, pos_b);
+
+    let mut gate = world.write_storage::<JumpGate>();
+    gate.insert(a, JumpGate { destination: b })
+        .expect("gate was just created");
+    gate.insert(b, JumpGate { destination: a })
+        .expect("gate was just created");
+
+    (a, b)
+}
+
+fn create_jump_gate(world: &mut World, sector: Sector, pos: Position) -> Entity {
+    world
+        .create_entity()
+        .with(sector)
+        .with(pos)
+        .with(Hitbox::new(Translation2::new(30., 30.)))
+        .build()
+}
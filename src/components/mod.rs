@@ -1,28 +1,53 @@
 pub mod base;
+pub mod brain;
 pub mod cargo;
-pub mod ui;
+pub mod celestial;
+pub mod faction;
+pub mod galaxy;
 
 pub use base::*;
+pub use brain::*;
 pub use cargo::*;
-pub use ui::*;
+pub use celestial::*;
+pub use faction::*;
+pub use galaxy::*;
 
-use amethyst::{
-    assets::Handle,
-    core::transform::Transform,
-    ecs::{Builder, Component, Entity, NullStorage, VecStorage, World, WorldExt},
-    renderer::{SpriteRender, SpriteSheet},
-};
+use amethyst::ecs::{Builder, Component, Entity, NullStorage, VecStorage, World, WorldExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::ships::{ShipClass, ShipClasses};
 
 #[derive(Debug)]
 pub enum ShipBehaviour {
     Idle,
     FlyTo(Entity),
+    /// Fly along the waypoints towards the target, consuming them as they are reached.
+    FollowPath(Entity, VecDeque<Point2>),
+    /// Pass through the jump gate, then continue towards the target.
+    Jump(Entity, Entity),
 }
 
 impl Component for ShipBehaviour {
     type Storage = VecStorage<Self>;
 }
 
+/// Name of the script deciding where an idle ship goes, replacing the behaviour tree of its `Brain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scripted(pub String);
+
+impl Component for Scripted {
+    type Storage = VecStorage<Self>;
+}
+
+/// Name shown to the player, see `names::NameGenerator`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+impl Component for Name {
+    type Storage = VecStorage<Self>;
+}
+
 #[derive(Default)]
 pub struct Station;
 
@@ -37,81 +62,227 @@ impl Component for Trader {
     type Storage = NullStorage<Self>;
 }
 
-pub type Parent = amethyst::core::transform::Parent;
-pub type ParentHierarchy = amethyst::core::transform::ParentHierarchy;
+/// Class of a ship, along with the stats it has from it, see `ships::ShipClasses`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShipStats {
+    pub class: String,
+    pub max_speed: f32,
+    pub thrust: f32,
+    pub turn_rate: f32,
+    pub fuel_tank: CargoUnits,
+}
+
+impl ShipStats {
+    pub fn new(class: &str, stats: &ShipClass) -> Self {
+        Self {
+            class: class.to_owned(),
+            max_speed: stats.max_speed,
+            thrust: stats.thrust,
+            turn_rate: stats.turn_rate,
+            fuel_tank: CargoUnits(stats.fuel_tank),
+        }
+    }
+}
+
+impl Component for ShipStats {
+    type Storage = VecStorage<Self>;
+}
 
-fn compute_name(target: &Entity, name: &str) -> String {
-    format!("{}-{}-{}", name, target.gen().id(), target.id())
+/// All recipes known to the simulation.
+///
+/// Together they form complete production chains: every cargo type is produced by some recipe, starting from ores
+/// which are mined without any ingredients.
+pub static RECIPES: &[FabricationRecipe] = &[
+    FabricationRecipe {
+        name: "carbon_ore",
+        duration: Time(50),
+        ingredients: &[],
+        products: &[(CargoType::CarbonOre, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "metal_ore",
+        duration: Time(50),
+        ingredients: &[],
+        products: &[(CargoType::MetalOre, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "ice_ore",
+        duration: Time(50),
+        ingredients: &[],
+        products: &[(CargoType::IceOre, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "carbon",
+        duration: Time(50),
+        ingredients: &[(CargoType::CarbonOre, CargoUnits(5))],
+        products: &[(CargoType::Carbon, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "metal",
+        duration: Time(50),
+        ingredients: &[(CargoType::MetalOre, CargoUnits(5))],
+        products: &[(CargoType::Metal, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "water",
+        duration: Time(50),
+        ingredients: &[(CargoType::IceOre, CargoUnits(4))],
+        products: &[
+            (CargoType::Water, CargoUnits(2)),
+            (CargoType::Nitrogen, CargoUnits(1)),
+        ],
+    },
+    FabricationRecipe {
+        name: "oxygen",
+        duration: Time(100),
+        ingredients: &[(CargoType::Water, CargoUnits(2))],
+        products: &[(CargoType::Oxygen, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "fuel",
+        duration: Time(100),
+        ingredients: &[
+            (CargoType::Water, CargoUnits(2)),
+            (CargoType::Carbon, CargoUnits(1)),
+        ],
+        products: &[(CargoType::Fuel, CargoUnits(2))],
+    },
+    FabricationRecipe {
+        name: "food",
+        duration: Time(150),
+        ingredients: &[
+            (CargoType::Water, CargoUnits(2)),
+            (CargoType::Nitrogen, CargoUnits(1)),
+            (CargoType::Carbon, CargoUnits(1)),
+        ],
+        products: &[
+            (CargoType::Food, CargoUnits(2)),
+            (CargoType::Waste, CargoUnits(1)),
+        ],
+    },
+    FabricationRecipe {
+        name: "recycling",
+        duration: Time(100),
+        ingredients: &[(CargoType::Waste, CargoUnits(4))],
+        products: &[
+            (CargoType::Carbon, CargoUnits(1)),
+            (CargoType::Water, CargoUnits(1)),
+        ],
+    },
+    FabricationRecipe {
+        name: "electronics",
+        duration: Time(150),
+        ingredients: &[
+            (CargoType::Metal, CargoUnits(1)),
+            (CargoType::Carbon, CargoUnits(1)),
+        ],
+        products: &[(CargoType::Electronics, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "robotics",
+        duration: Time(200),
+        ingredients: &[
+            (CargoType::Metal, CargoUnits(2)),
+            (CargoType::Electronics, CargoUnits(2)),
+        ],
+        products: &[(CargoType::Robotics, CargoUnits(1))],
+    },
+    FabricationRecipe {
+        name: "hullplating",
+        duration: Time(100),
+        ingredients: &[(CargoType::Metal, CargoUnits(4))],
+        products: &[(CargoType::Hullplating, CargoUnits(1))],
+    },
+];
+
+pub fn recipe_by_name(name: &str) -> Option<&'static FabricationRecipe> {
+    RECIPES.iter().find(|recipe| recipe.name == name)
 }
 
-static METAL_ORE_RECIPE: FabricationRecipe = FabricationRecipe {
-    duration: Time(50),
-    ingredients: &[],
-    products: &[(CargoType::MetalOre, CargoUnits(1))],
-};
-static METAL_RECIPE: FabricationRecipe = FabricationRecipe {
-    duration: Time(50),
-    ingredients: &[(CargoType::MetalOre, CargoUnits(5))],
-    products: &[(CargoType::Metal, CargoUnits(1))],
-};
-
-pub fn create_station(world: &mut World, pos: Position) -> Entity {
-    let sprite_number = 1;
-    let sprite_sheet = (*world.fetch::<Handle<SpriteSheet>>()).clone();
+pub const STATION_CAPACITY: CargoUnits = CargoUnits(1000000);
 
+pub fn create_station(
+    world: &mut World,
+    sector: Sector,
+    pos: Position,
+    orbit: Option<Orbit>,
+    recipe: Option<&'static FabricationRecipe>,
+    owner: Option<Faction>,
+) -> Entity {
     let (width, height) = (51., 58.);
     let hitbox = Hitbox::new(Translation2::new(width, height));
 
-    let res = world
+    // Stations rotate, hence keep clear of the whole diagonal.
+    let obstacle = Obstacle::new(f32::sqrt(width * width + height * height) / 2.);
+
+    let mut res = world
         .create_entity()
         .with(Station)
+        .with(sector)
         .with(pos)
-        .with(SpriteRender {
-            sprite_sheet,
-            sprite_number,
-        })
         .with(hitbox)
-        .with(Transform::default())
+        .with(obstacle)
         .with(Angle::new(f32::default()))
         .with(AngularMomentum::new(0.001))
-        .with(Cargo::new(CargoUnits(1000000)))
-        .with(FabricationModule {
-            progress: None,
-            recipe: &METAL_ORE_RECIPE,
-        })
-        .build();
+        .with(Cargo::new(STATION_CAPACITY));
 
-    let anchor = create_ui_anchor(world, &res, "station", true);
-    create_ui_label(world, &anchor, compute_name(&res, "station"));
+    if let Some(orbit) = orbit {
+        res = res.with(orbit);
+    }
 
-    res
-}
+    if let Some(recipe) = recipe {
+        res = res.with(FabricationModule {
+            progress: None,
+            recipe,
+        });
+    }
 
-pub fn create_trader(world: &mut World, pos: Position, behaviour: ShipBehaviour) -> Entity {
-    let sprite_number = 0;
-    let sprite_sheet = (*world.fetch::<Handle<SpriteSheet>>()).clone();
+    if let Some(owner) = owner {
+        res = res.with(owner);
+    }
 
-    let (width, height) = (39., 57.);
+    res.build()
+}
 
-    let hitbox = Hitbox::new(Translation2::new(width, height));
+/// Create a ship of the given class, which idles until its behaviour tree sends it somewhere.
+///
+/// The class is looked up in the `ShipClasses` resource. Returns `None`, creating nothing, if it does not exist.
+pub fn create_ship(
+    world: &mut World,
+    class: &str,
+    sector: Sector,
+    pos: Position,
+    owner: Faction,
+) -> Option<Entity> {
+    let stats = world
+        .entry::<ShipClasses>()
+        .or_insert_with(ShipClasses::default)
+        .get(class)
+        .cloned()?;
+    let (width, height) = stats.hitbox;
 
-    let res = world
+    let ship = world
         .create_entity()
         .with(Trader)
+        .with(ShipStats::new(class, &stats))
+        .with(sector)
         .with(pos)
         .with(Velocity::default())
-        .with(SpriteRender {
-            sprite_sheet,
-            sprite_number,
-        })
-        .with(hitbox)
-        .with(Transform::default())
-        .with(behaviour)
-        .with(Cargo::new(CargoUnits(100)))
+        .with(Hitbox::new(Translation2::new(width, height)))
+        .with(ShipBehaviour::Idle)
+        .with(Brain::new("trader"))
+        .with(owner)
+        .with(Cargo::new(CargoUnits(stats.capacity)))
         .build();
+    Some(ship)
+}
 
-    let anchor = create_ui_anchor(world, &res, "trader", true);
-    create_ui_label(world, &anchor, compute_name(&res, "trader"));
-
-    res
+/// Send the ship to the target, leaving its behaviour tree to wait for the ship to arrive.
+pub fn send_to(world: &World, ship: Entity, target: Entity) {
+    if let Some(behaviour) = world.write_storage::<ShipBehaviour>().get_mut(ship) {
+        *behaviour = ShipBehaviour::FlyTo(target);
+    }
+    if let Some(brain) = world.write_storage::<Brain>().get_mut(ship) {
+        brain.blackboard.sent_to(target);
+    }
 }
@@ -0,0 +1,37 @@
+use amethyst::ecs::Entity;
+
+use crate::components::Sector;
+
+/// Noteworthy happening in the simulation, published to the `EventChannel<SimEvent>` resource.
+///
+/// Events only inform; the simulation never depends on anyone reading them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimEvent {
+    /// A ship reached the station or body it was flying to.
+    Arrived { ship: Entity, target: Entity },
+    /// A ship passed through a jump gate.
+    Jumped {
+        ship: Entity,
+        from: Sector,
+        to: Sector,
+    },
+    /// A station took the ingredients of its recipe from its cargo.
+    FabricationStarted {
+        station: Entity,
+        recipe: &'static str,
+    },
+    /// A station stored the products of its recipe in its cargo.
+    FabricationCompleted {
+        station: Entity,
+        recipe: &'static str,
+    },
+}
+
+/// Number of events published since the start of the simulation, per kind.
+#[derive(Debug, Default)]
+pub struct EventCounts {
+    pub arrivals: u64,
+    pub jumps: u64,
+    pub fabrications_started: u64,
+    pub fabrications_completed: u64,
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Credits, Faction};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactionInfo {
+    pub name: String,
+    pub credits: Credits,
+}
+
+/// Parties owning ships and stations, and their funds.
+#[derive(Debug, Default)]
+pub struct Factions {
+    factions: Vec<FactionInfo>,
+}
+
+impl Factions {
+    pub fn add_faction(&mut self, name: &str, credits: Credits) -> Faction {
+        self.factions.push(FactionInfo {
+            name: name.to_owned(),
+            credits,
+        });
+        Faction(self.factions.len() as u32 - 1)
+    }
+
+    pub fn factions(&self) -> impl Iterator<Item = (Faction, &FactionInfo)> {
+        self.factions
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (Faction(i as u32), info))
+    }
+
+    pub fn get(&self, faction: Faction) -> Option<&FactionInfo> {
+        self.factions.get(faction.0 as usize)
+    }
+
+    pub fn get_mut(&mut self, faction: Faction) -> Option<&mut FactionInfo> {
+        self.factions.get_mut(faction.0 as usize)
+    }
+}
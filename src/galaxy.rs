@@ -0,0 +1,95 @@
+use amethyst::ecs::Entity;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::components::{Point2, Sector};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectorInfo {
+    pub name: String,
+    /// Location on the galaxy map.
+    pub position: Point2,
+}
+
+/// Sectors making up the universe, and the routes between them through jump gates.
+#[derive(Debug, Default)]
+pub struct Galaxy {
+    sectors: Vec<SectorInfo>,
+    gates: Vec<(Entity, Sector, Sector)>,
+    routes: HashMap<(Sector, Sector), Option<Vec<Entity>>>,
+}
+
+impl Galaxy {
+    pub fn add_sector(&mut self, name: &str, position: Point2) -> Sector {
+        self.sectors.push(SectorInfo {
+            name: name.to_owned(),
+            position,
+        });
+        Sector(self.sectors.len() as u32 - 1)
+    }
+
+    pub fn sectors(&self) -> impl Iterator<Item = (Sector, &SectorInfo)> {
+        self.sectors
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (Sector(i as u32), info))
+    }
+
+    pub fn get(&self, sector: Sector) -> Option<&SectorInfo> {
+        self.sectors.get(sector.0 as usize)
+    }
+
+    /// Sector following the given one, wrapping around at the end.
+    pub fn next(&self, sector: Sector) -> Sector {
+        Sector((sector.0 + 1) % self.sectors.len().max(1) as u32)
+    }
+
+    /// Provide all jump gates, with the sector they are in and the sector they lead to.
+    pub fn update(&mut self, gates: Vec<(Entity, Sector, Sector)>) {
+        if self.gates != gates {
+            self.gates = gates;
+            self.routes.clear();
+        }
+    }
+
+    /// Jump gates to pass through, in order, to get from one sector to another.
+    ///
+    /// Yields None when no route exists.
+    pub fn route(&mut self, from: Sector, to: Sector) -> Option<&[Entity]> {
+        let gates = &self.gates;
+        self.routes
+            .entry((from, to))
+            .or_insert_with(|| {
+                // Breadth-first, such that the route with the fewest jumps is chosen.
+                let mut came_from: HashMap<Sector, (Sector, Entity)> = HashMap::new();
+                let mut open = VecDeque::new();
+                open.push_back(from);
+
+                while let Some(current) = open.pop_front() {
+                    if current == to {
+                        let mut res = Vec::new();
+                        let mut current = to;
+                        while let Some(&(previous, gate)) = came_from.get(&current) {
+                            res.push(gate);
+                            current = previous;
+                        }
+                        res.reverse();
+                        return Some(res);
+                    }
+
+                    for &(gate, gate_sector, destination) in gates.iter() {
+                        if gate_sector == current
+                            && destination != from
+                            && !came_from.contains_key(&destination)
+                        {
+                            came_from.insert(destination, (current, gate));
+                            open.push_back(destination);
+                        }
+                    }
+                }
+
+                None
+            })
+            .as_deref()
+    }
+}
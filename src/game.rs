@@ -1,20 +1,35 @@
 use amethyst::{
     assets::{AssetStorage, Handle, Loader},
-    core::transform::Transform,
-    ecs::ReadStorage,
-    input::{is_close_requested, is_key_down, InputEvent, ScrollDirection},
+    config::Config,
+    core::{timing::Time as FrameTime, transform::Transform, ArcThreadPool},
+    ecs::{join::Join, Dispatcher, Entities, Entity, ReadStorage},
+    input::{is_close_requested, Bindings, InputEvent, InputHandler, StringBindings},
     prelude::*,
     renderer::{Camera, ImageFormat, SpriteSheet, SpriteSheetFormat, Texture},
-    ui::{FontAsset, TtfFormat, UiEvent, UiEventType},
-    winit::{MouseButton, VirtualKeyCode},
+    ui::{FontAsset, TtfFormat, UiEvent, UiEventType, UiText},
+    utils::application_root_dir,
 };
-use log::info;
+use log::{error, info};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::components::*;
-use crate::resources::*;
-
-pub const ARENA_HEIGHT: f32 = 1000.0;
-pub const ARENA_WIDTH: f32 = 1000.0;
+use crate::presentation::{self, *};
+use crate::setup::{Setup, Start};
+use traders::commands::{Command, PendingCommands};
+use traders::components::*;
+use traders::galaxy::Galaxy;
+use traders::hot_reload::FileWatcher;
+use traders::names::{self, NameGenerator};
+use traders::navigation::Navigation;
+use traders::profiling;
+use traders::recipes::Recipes;
+use traders::replay::{Recording, ReplayState, Session};
+use traders::resources::*;
+use traders::savegame::{SaveGame, SavedView};
+use traders::scenario::Scenario;
+use traders::scripting::Scripts;
+use traders::ships::ShipClasses;
+use traders::simulation;
 
 const DELTA: f32 = 0.00001;
 
@@ -59,56 +74,501 @@ fn load_font(world: &mut World) -> Handle<FontAsset> {
     loader.load("fonts/square.ttf", TtfFormat, (), &store)
 }
 
-pub struct Game;
+fn quicksave_path() -> amethyst::Result<PathBuf> {
+    Ok(application_root_dir()?.join("saves").join("quicksave.ron"))
+}
+
+fn quicksave(world: &World) -> amethyst::Result<PathBuf> {
+    let mut save = SaveGame::capture(world);
+    let camera_state = world.fetch::<CameraState>();
+    save.view = Some(SavedView {
+        zoom: camera_state.zoom,
+        sector: camera_state.sector,
+        follow: match camera_state.behaviour {
+            CameraBehaviour::Follow(target) => save.entity_ref(target),
+            _ => None,
+        },
+    });
+
+    let path = quicksave_path()?;
+    save.save(&path)?;
+    Ok(path)
+}
+
+/// Replace the current simulation by the quicksaved one, leaving it as it was if the save cannot be loaded.
+///
+/// This is refused while recording or replaying, as the recording could not be replayed from its start.
+fn quickload(world: &mut World) -> amethyst::Result<PathBuf> {
+    if world.has_value::<Recording>() || world.has_value::<ReplayState>() {
+        return Err(amethyst::Error::from_string(
+            "cannot load while recording or replaying",
+        ));
+    }
+
+    let path = quicksave_path()?;
+    let save = SaveGame::load(&path)?;
+    save.validate(world)?;
+    let view = save.view.clone();
+
+    presentation::clear(world);
+    simulation::reset_reports(world);
+    world.fetch_mut::<PendingCommands>().0.clear();
+    let entities = save.apply(world);
+    world.read_resource::<Recipes>().apply(world);
+    world.read_resource::<ShipClasses>().apply(world);
+    presentation::decorate(world);
+
+    if let Some(view) = view {
+        *world.fetch_mut::<CameraState>() = camera_state(&view, &entities);
+    }
+
+    Ok(path)
+}
+
+/// Replace the current simulation by a fresh start of the scenario.
+///
+/// Pending commands are dropped, as they refer to entities of the universe being replaced.
+fn restart(world: &mut World, scenario: &Scenario, seed: u64) {
+    presentation::clear(world);
+    simulation::reset_reports(world);
+    world.fetch_mut::<PendingCommands>().0.clear();
+    world.insert(CurrentTime::default());
+    world.insert(SimRng::seed_from_u64(seed));
+    world.insert(Navigation::default());
+    world.insert(NameGenerator::new(seed));
+
+    let followed = scenario.create(world);
+    world.read_resource::<Recipes>().apply(world);
+    presentation::decorate(world);
+
+    let mut camera_state = world.fetch_mut::<CameraState>();
+    camera_state.behaviour = match followed {
+        Some(ship) => CameraBehaviour::Follow(ship),
+        None => CameraBehaviour::Static,
+    };
+}
+
+/// Camera looking at the given view, which refers to the given entities.
+fn camera_state(view: &SavedView, entities: &[Entity]) -> CameraState {
+    CameraState {
+        zoom: view.zoom,
+        sector: view.sector,
+        behaviour: match view.follow {
+            Some(target) => CameraBehaviour::Follow(entities[target.0 as usize]),
+            None => CameraBehaviour::Static,
+        },
+    }
+}
+
+fn profile_path() -> amethyst::Result<PathBuf> {
+    Ok(application_root_dir()?.join("profile.txt"))
+}
+
+/// Main state, running the simulation at a fixed rate regardless of the frame rate.
+pub struct Game {
+    setup: Setup,
+    /// Speed to start simulating at.
+    speed: SimulationSpeed,
+    /// Whether the profile is shown on screen.
+    show_profile: bool,
+    /// Whether the systems of the dispatcher are profiled, which they are once the profile was asked for.
+    profiled: bool,
+    dispatcher: Option<Dispatcher<'static, 'static>>,
+    /// Real time which has passed but has not yet been simulated.
+    accumulator: Duration,
+    /// Data files to reload when they change, unless recording or replaying.
+    watcher: Option<FileWatcher>,
+}
+
+impl Game {
+    pub fn new(setup: Setup, speed: SimulationSpeed) -> Self {
+        Self {
+            setup,
+            speed,
+            show_profile: false,
+            profiled: false,
+            dispatcher: None,
+            accumulator: Duration::default(),
+            watcher: None,
+        }
+    }
+
+    /// Replace the dispatcher by one which profiles its systems or not.
+    ///
+    /// Systems only hold event readers besides their configuration, so this may happen between any two ticks.
+    fn build_dispatcher(&mut self, world: &mut World, profile: bool) {
+        let mut dispatcher = simulation::dispatcher(profile)
+            .with_pool((*world.read_resource::<ArcThreadPool>()).clone())
+            .build();
+        dispatcher.setup(world);
+        self.dispatcher = Some(dispatcher);
+        self.profiled = profile;
+    }
+
+    /// Reload the data files which changed since they were last polled.
+    ///
+    /// Files which fail to load or validate are reported and otherwise ignored, such that the game continues with
+    /// what it had.
+    fn hot_reload(&mut self, world: &mut World) {
+        let changed = match self.watcher.as_mut() {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+
+        for path in changed {
+            match self.reload(world, &path) {
+                Ok(true) => info!("Reloaded {:?}.", path),
+                Ok(false) => (),
+                Err(e) => error!("Could not reload {:?}: {}.", path, e),
+            }
+        }
+    }
+
+    /// Load the file into its resource, if it is one of the data files. Yields whether it was.
+    fn reload(&mut self, world: &mut World, path: &Path) -> amethyst::Result<bool> {
+        let app_root = application_root_dir()?;
+
+        if path == app_root.join("assets").join("recipes.ron") {
+            let recipes = Recipes::load(path)?;
+            recipes.apply(world);
+            world.insert(recipes);
+        } else if path == app_root.join("assets").join("ships.ron") {
+            let classes = ShipClasses::load(path)?;
+            classes.apply(world);
+            world.insert(classes);
+            presentation::update_ship_sprites(world);
+        } else if path == app_root.join("config").join("bindings.ron") {
+            let bindings = Bindings::<StringBindings>::load(path)?;
+            world
+                .write_resource::<InputHandler<StringBindings>>()
+                .bindings = bindings;
+        } else if self.setup.scenario_path.as_deref() == Some(path) {
+            let scenario = Scenario::load(
+                path,
+                &world.read_resource::<ShipClasses>(),
+                &world.read_resource::<Scripts>(),
+            )?;
+            restart(world, &scenario, self.setup.config.seed);
+            self.setup.start = Start::Scenario(scenario);
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Carry out an action bound in `config/bindings.ron`.
+    fn perform(&mut self, world: &mut World, action: &str) -> SimpleTrans {
+        match action {
+            "quit" => return Trans::Quit,
+            "zoom_in" => zoom(world, -0.1),
+            "zoom_out" => zoom(world, 0.1),
+            "select" => {
+                let hover_selectable = world.fetch::<HoverSelectable>();
+                let mut camera_state = world.fetch_mut::<CameraState>();
+
+                if hover_selectable.0.len() == 1 {
+                    if let Some(target) = hover_selectable.0.iter().next() {
+                        camera_state.behaviour = CameraBehaviour::Follow(*target);
+                    }
+                }
+            }
+            "order" => {
+                // Order the followed ship to fly to the hovered station.
+                let (trader, station, name): (
+                    ReadStorage<Trader>,
+                    ReadStorage<Station>,
+                    ReadStorage<Name>,
+                ) = world.system_data();
+                let hover_selectable = world.fetch::<HoverSelectable>();
+                let camera_state = world.fetch::<CameraState>();
+
+                if let CameraBehaviour::Follow(ship) = camera_state.behaviour {
+                    let target = hover_selectable.0.iter().find(|&&e| station.contains(e));
+                    if let (true, Some(target)) = (trader.contains(ship), target) {
+                        world.fetch_mut::<PendingCommands>().0.push(Command::FlyTo {
+                            ship: ship.id(),
+                            target: target.id(),
+                        });
+                        info!(
+                            "Ordered {} to fly to {}.",
+                            names::describe(ship, name.get(ship)),
+                            names::describe(*target, name.get(*target))
+                        );
+                    }
+                }
+            }
+            "follow_next" => follow_next(world),
+            "next_sector" => {
+                let galaxy = world.fetch::<Galaxy>();
+                let mut camera_state = world.fetch_mut::<CameraState>();
+
+                camera_state.sector = galaxy.next(camera_state.sector);
+                camera_state.behaviour = CameraBehaviour::Static;
+
+                if let Some(info) = galaxy.get(camera_state.sector) {
+                    info!("Viewing sector {}.", info.name);
+                }
+            }
+            "pause" => {
+                let mut speed = world.fetch_mut::<SimulationSpeed>();
+                let mut resume = world.fetch_mut::<ResumeSpeed>();
+                if *speed == SimulationSpeed::Paused {
+                    *speed = resume.0;
+                } else {
+                    speed.change(SimulationSpeed::Paused, &mut resume);
+                }
+                info!("Simulation speed: {:?}.", *speed);
+            }
+            "step" => {
+                let mut speed = world.fetch_mut::<SimulationSpeed>();
+                speed.change(
+                    SimulationSpeed::Paused,
+                    &mut world.fetch_mut::<ResumeSpeed>(),
+                );
+                world.fetch_mut::<PendingSteps>().0 += 1;
+            }
+            "faster" => {
+                let mut speed = world.fetch_mut::<SimulationSpeed>();
+                let faster = speed.faster();
+                speed.change(faster, &mut world.fetch_mut::<ResumeSpeed>());
+                info!("Simulation speed: {:?}.", *speed);
+            }
+            "slower" => {
+                let mut speed = world.fetch_mut::<SimulationSpeed>();
+                let slower = speed.slower();
+                speed.change(slower, &mut world.fetch_mut::<ResumeSpeed>());
+                info!("Simulation speed: {:?}.", *speed);
+            }
+            "quicksave" => match quicksave(world) {
+                Ok(path) => info!("Saved to {:?}.", path),
+                Err(e) => error!("Could not save: {}.", e),
+            },
+            "quickload" => match quickload(world) {
+                Ok(path) => info!("Loaded {:?}.", path),
+                Err(e) => error!("Could not load: {}.", e),
+            },
+            "toggle_profile" => {
+                self.show_profile = !self.show_profile;
+                if self.show_profile && !self.profiled {
+                    self.build_dispatcher(world, true);
+                }
+                if !self.show_profile {
+                    let overlay = world.read_resource::<ProfileOverlay>().0;
+                    if let Some(text) = world.write_storage::<UiText>().get_mut(overlay) {
+                        text.text.clear();
+                    }
+                }
+            }
+            "write_profile" => {
+                let path = match &self.setup.profile {
+                    Some(path) => Ok(path.clone()),
+                    None => profile_path(),
+                };
+                match path.and_then(|path| {
+                    profiling::write_report(world, &path)?;
+                    Ok(path)
+                }) {
+                    Ok(path) => info!("Profile written to {:?}.", path),
+                    Err(e) => error!("Could not write profile: {}.", e),
+                }
+            }
+            _ => (),
+        }
+        Trans::None
+    }
+}
+
+fn zoom(world: &mut World, delta: f32) {
+    let mut camera_state = world.fetch_mut::<CameraState>();
+    camera_state.zoom = f32::min(f32::max(0.1, camera_state.zoom + delta), 2.0);
+}
+
+/// Pan the camera while any of the pan actions is held.
+fn pan_camera(world: &mut World) {
+    let input = world.read_resource::<InputHandler<StringBindings>>();
+    let held = |action: &str| input.action_is_down(action).unwrap_or(false);
+
+    let mut t = Translation2::new(0., 0.);
+    if held("pan_up") {
+        t.y += 1.;
+    }
+    if held("pan_down") {
+        t.y -= 1.;
+    }
+    if held("pan_left") {
+        t.x -= 1.;
+    }
+    if held("pan_right") {
+        t.x += 1.;
+    }
+
+    let mut camera_state = world.fetch_mut::<CameraState>();
+    if nalgebra_glm::length(&t.vector) > DELTA {
+        camera_state.behaviour = CameraBehaviour::Pan(t);
+    } else if let CameraBehaviour::Pan(_) = camera_state.behaviour {
+        camera_state.behaviour = CameraBehaviour::Static;
+    }
+}
+
+/// Follow the next ship in the viewed sector, in order of entity.
+fn follow_next(world: &mut World) {
+    let (entities, trader, sector): (Entities, ReadStorage<Trader>, ReadStorage<Sector>) =
+        world.system_data();
+    let mut camera_state = world.fetch_mut::<CameraState>();
+
+    let current = match camera_state.behaviour {
+        CameraBehaviour::Follow(e) => Some(e.id()),
+        _ => None,
+    };
+    let ships: Vec<Entity> = (&entities, &trader, &sector)
+        .join()
+        .filter(|&(_, _, &s)| s == camera_state.sector)
+        .map(|(e, _, _)| e)
+        .collect();
+
+    let next = ships
+        .iter()
+        .find(|e| current.map_or(true, |current| e.id() > current))
+        .or_else(|| ships.first());
+    if let Some(&next) = next {
+        camera_state.behaviour = CameraBehaviour::Follow(next);
+    }
+}
 
 impl SimpleState for Game {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let world = data.world;
 
+        self.build_dispatcher(world, self.setup.profile.is_some());
+
         let spritesheet = load_sprite_sheet(world);
         world.insert(spritesheet);
         let font = load_font(world);
         world.insert(font);
         world.insert(CurrentTime::default());
+        world.insert(self.speed);
+        world.insert(ResumeSpeed::default());
+        world.insert(PendingSteps::default());
+        world.insert(SimRng::seed_from_u64(self.setup.config.seed));
         world.insert(Selection::default());
         world.insert(HoverSelectable::default());
 
         initialise_camera(world);
+        create_notification_area(world);
+        create_profile_overlay(world);
 
-        world.register::<Position>();
-        world.register::<Velocity>();
-        world.register::<Trader>();
-        world.register::<Station>();
-        world.register::<ShipBehaviour>();
+        simulation::register_components(world);
         world.register::<Parent>();
-        world.register::<Hitbox>();
-        world.register::<Cargo>();
-        world.register::<FabricationModule>();
         world.register::<UiRelative>();
         world.register::<UiSelectable>();
 
-        create_station(world, Position::new(Point2::new(800., 700.)));
-        create_station(world, Position::new(Point2::new(300., 100.)));
-        create_station(world, Position::new(Point2::new(200., 600.)));
-
-        create_trader(
-            world,
-            Position::new(Point2::new(900., 900.)),
-            ShipBehaviour::Idle,
-        );
-
-        let t1 = create_trader(
-            world,
-            Position::new(Point2::new(50., 50.)),
-            ShipBehaviour::Idle,
-        );
-
-        let camera_state = CameraState {
-            zoom: 1.0,
-            behaviour: CameraBehaviour::Follow(t1),
+        let view = match self.setup.begin(world) {
+            Ok(view) => view,
+            Err(e) => {
+                error!("Could not create the universe: {}.", e);
+                None
+            }
+        };
+        presentation::decorate(world);
+
+        let camera_state = match view {
+            Some((view, entities)) => camera_state(&view, &entities),
+            None => CameraState {
+                zoom: 1.0,
+                sector: Sector::default(),
+                behaviour: CameraBehaviour::Static,
+            },
         };
 
         world.insert(camera_state);
+
+        // Changing the rules halfway would make recordings impossible to replay.
+        if let Session::Live = self.setup.session {
+            match application_root_dir() {
+                Ok(app_root) => {
+                    let mut dirs = vec![app_root.join("assets"), app_root.join("config")];
+                    dirs.extend(
+                        self.setup
+                            .scenario_path
+                            .as_ref()
+                            .and_then(|path| path.parent())
+                            .map(Path::to_owned),
+                    );
+                    dirs.dedup();
+                    self.watcher = Some(FileWatcher::new(dirs, Duration::from_secs(1)));
+                }
+                Err(e) => error!("Could not watch data files: {}.", e),
+            }
+        }
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if let Err(e) = self.setup.finish(data.world) {
+            error!("Could not finish: {}.", e);
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = &mut *data.world;
+
+        self.setup.process_requests(world);
+        self.hot_reload(world);
+
+        let delta = world.read_resource::<FrameTime>().delta_real_time();
+        let speed = *world.read_resource::<SimulationSpeed>();
+        let dispatcher = match self.dispatcher.as_mut() {
+            Some(dispatcher) => dispatcher,
+            None => return Trans::None,
+        };
+
+        match speed {
+            SimulationSpeed::Paused => {
+                self.accumulator = Duration::default();
+
+                let steps = std::mem::take(&mut world.write_resource::<PendingSteps>().0);
+                for _ in 0..steps {
+                    simulation::tick(dispatcher, world);
+                }
+            }
+            SimulationSpeed::Times(factor) => {
+                self.accumulator += delta * factor;
+
+                let mut ticks = 0;
+                while self.accumulator >= simulation::TICK
+                    && ticks < simulation::MAX_TICKS_PER_FRAME.saturating_mul(factor)
+                {
+                    simulation::tick(dispatcher, world);
+                    self.accumulator -= simulation::TICK;
+                    ticks += 1;
+                }
+
+                // Drop whatever could not be caught up with, rather than accumulating an ever growing backlog.
+                if self.accumulator >= simulation::TICK {
+                    self.accumulator = Duration::default();
+                }
+            }
+            SimulationSpeed::Max => {
+                self.accumulator = Duration::default();
+
+                let start = Instant::now();
+                while start.elapsed() < simulation::MAX_FRAME_BUDGET {
+                    simulation::tick(dispatcher, world);
+                }
+            }
+        }
+
+        pan_camera(world);
+
+        if self.show_profile {
+            let report = profiling::report(world);
+            let overlay = world.read_resource::<ProfileOverlay>().0;
+            if let Some(text) = world.write_storage::<UiText>().get_mut(overlay) {
+                text.text = report;
+            }
+        }
+
+        Trans::None
     }
 
     fn handle_event(
@@ -120,7 +580,7 @@ impl SimpleState for Game {
 
         match &event {
             StateEvent::Window(event) => {
-                if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                if is_close_requested(&event) {
                     Trans::Quit
                 } else {
                     Trans::None
@@ -153,83 +613,11 @@ impl SimpleState for Game {
                 }
                 Trans::None
             }
-            StateEvent::Input(input) => {
-                use InputEvent::*;
-                match input {
-                    MouseWheelMoved(dir) => {
-                        let dir = match *dir {
-                            ScrollDirection::ScrollUp => -0.1,
-                            ScrollDirection::ScrollDown => 0.1,
-                            _ => 0.0,
-                        };
-                        let mut camera_state = world.fetch_mut::<CameraState>();
-                        camera_state.zoom = f32::min(f32::max(0.1, camera_state.zoom + dir), 2.0);
-                    }
-                    MouseButtonPressed(MouseButton::Left) => {
-                        let hover_selectable = world.fetch::<HoverSelectable>();
-                        let mut camera_state = world.fetch_mut::<CameraState>();
-
-                        if hover_selectable.0.len() == 1 {
-                            if let Some(target) = hover_selectable.0.iter().next() {
-                                camera_state.behaviour = CameraBehaviour::Follow(*target);
-                            }
-                        }
-
-                        info!("Press detected: {:?} {:?}.", input, hover_selectable.0);
-                    }
-                    MouseMoved { .. }
-                    | CursorMoved { .. }
-                    | KeyTyped(_)
-                    | ButtonPressed(_)
-                    | ButtonReleased(_)
-                    | MouseButtonReleased(_) => (),
-                    KeyPressed { key_code, .. } => {
-                        let mut camera_state = world.fetch_mut::<CameraState>();
-                        let mut t = match camera_state.behaviour {
-                            CameraBehaviour::Pan(t) => t,
-                            _ => Translation2::new(0., 0.),
-                        };
-
-                        match key_code {
-                            VirtualKeyCode::W => t.y = 1.,
-                            VirtualKeyCode::A => t.x = -1.,
-                            VirtualKeyCode::S => t.y = -1.,
-                            VirtualKeyCode::D => t.x = 1.,
-                            _ => (),
-                        }
-
-                        if nalgebra_glm::length(&t.vector) > DELTA {
-                            camera_state.behaviour = CameraBehaviour::Pan(t);
-                        }
-                    }
-                    KeyReleased { key_code, .. } => {
-                        let mut camera_state = world.fetch_mut::<CameraState>();
-
-                        match camera_state.behaviour {
-                            CameraBehaviour::Pan(mut t) => {
-                                match key_code {
-                                    VirtualKeyCode::W => t.y = 0.,
-                                    VirtualKeyCode::A => t.x = 0.,
-                                    VirtualKeyCode::S => t.y = 0.,
-                                    VirtualKeyCode::D => t.x = 0.,
-                                    _ => (),
-                                }
-
-                                if nalgebra_glm::length(&t.vector) > DELTA {
-                                    camera_state.behaviour = CameraBehaviour::Pan(t);
-                                } else {
-                                    camera_state.behaviour = CameraBehaviour::Static;
-                                }
-                            }
-                            _ => (),
-                        }
-                    }
-                    _ => {
-                        info!("Input Event detected: {:?}.", input);
-                    }
-                }
-                Trans::None
+            StateEvent::Input(InputEvent::ActionPressed(action))
+            | StateEvent::Input(InputEvent::ActionWheelMoved(action)) => {
+                self.perform(world, action)
             }
+            StateEvent::Input(_) => Trans::None,
         }
     }
 }
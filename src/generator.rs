@@ -0,0 +1,303 @@
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+use crate::components::*;
+use crate::scenario::*;
+
+const STAR_NAMES: &[&str] = &[
+    "Sol", "Kepler", "Vega", "Altair", "Deneb", "Rigel", "Sirius", "Procyon", "Capella",
+    "Arcturus", "Antares", "Pollux",
+];
+const FACTION_NAMES: &[&str] = &["Merchants Guild", "Miners Union", "Orion Industries"];
+/// Classes in `assets/ships.ron` which ships are drawn from.
+const SHIP_CLASSES: &[&str] = &["hauler", "miner", "freighter", "courier"];
+const PLANET_SUFFIXES: &[&str] = &["b", "c", "d", "e"];
+
+/// Distance to keep from the edges of the arena.
+const MARGIN: f32 = 60.;
+/// Distance to keep between stations and gates which do not orbit anything.
+const SPACING: f32 = 120.;
+/// Largest orbit of a planet, such that the area beyond it remains free for static stations and gates.
+const MAX_PLANET_ORBIT: f32 = 380.;
+const STAR_POSITION: (f32, f32) = (ARENA_WIDTH / 2., ARENA_HEIGHT / 2.);
+
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub sectors: u32,
+    /// Number of stations over all sectors. At least one station is built for every recipe.
+    pub stations: u32,
+    /// Size of the fleet relative to the number of stations.
+    pub ships_per_station: u32,
+    pub player_credits: i64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            sectors: 3,
+            stations: 20,
+            ships_per_station: 2,
+            player_credits: 10000,
+        }
+    }
+}
+
+struct SectorLayout {
+    def: SectorDef,
+    /// Planets which stations may orbit.
+    planets: Vec<String>,
+    /// Stations and gates which do not orbit anything, to keep clear of.
+    occupied: Vec<(f32, f32)>,
+}
+
+/// Generate a universe with a complete economy, such that every recipe is fabricated somewhere.
+///
+/// Mining stations get an asteroid field of their ore nearby. The same seed and config always yield the same scenario.
+pub fn generate(seed: u64, config: &GeneratorConfig) -> Scenario {
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
+
+    let mut factions = vec![FactionDef {
+        name: "Player".to_owned(),
+        credits: config.player_credits,
+    }];
+    factions.extend(FACTION_NAMES.iter().map(|&name| FactionDef {
+        name: name.to_owned(),
+        credits: 10000 * i64::from(config.stations),
+    }));
+
+    let mut sectors: Vec<SectorLayout> = (0..config.sectors.max(1) as usize)
+        .map(|i| generate_sector(&mut rng, i, config.sectors.max(1) as usize))
+        .collect();
+
+    let jump_gates = generate_jump_gates(&mut rng, &mut sectors);
+
+    let mut recipes: Vec<&'static FabricationRecipe> = RECIPES.iter().collect();
+    while recipes.len() < config.stations as usize {
+        recipes.push(RECIPES.choose(&mut rng).expect("there are recipes"));
+    }
+    recipes.shuffle(&mut rng);
+
+    for (i, recipe) in recipes.into_iter().enumerate() {
+        let layout = &mut sectors[i % config.sectors.max(1) as usize];
+        let owner = FACTION_NAMES.choose(&mut rng).map(|&name| name.to_owned());
+        let module = Some(recipe.name.to_owned());
+
+        // Mining stations stay put, such that their asteroid field remains nearby.
+        let location = if !recipe.ingredients.is_empty()
+            && !layout.planets.is_empty()
+            && rng.gen_bool(0.5)
+        {
+            LocationDef::Orbit {
+                center: layout.planets.choose(&mut rng).expect("checked").clone(),
+                radius: rng.gen_range(50., 80.),
+                period: rng.gen_range(4000, 9000),
+                phase: rng.gen_range(0., std::f32::consts::PI * 2.),
+            }
+        } else {
+            let (x, y) = free_position(&mut rng, &layout.occupied);
+            layout.occupied.push((x, y));
+
+            if recipe.ingredients.is_empty() {
+                for &(ore, _) in recipe.products {
+                    let angle = rng.gen_range(0., std::f32::consts::PI * 2.);
+                    let distance = rng.gen_range(80., 140.);
+                    layout.def.asteroid_fields.push(AsteroidFieldDef {
+                        position: clamp((x + distance * angle.cos(), y + distance * angle.sin())),
+                        radius: rng.gen_range(40., 80.),
+                        ore,
+                    });
+                }
+            }
+
+            LocationDef::At(x, y)
+        };
+
+        // Stations are left unnamed, such that they are named after where they are when created.
+        layout.def.stations.push(StationDef {
+            name: None,
+            location,
+            owner,
+            module,
+            cargo: Vec::new(),
+        });
+    }
+
+    let ships = config.stations * config.ships_per_station;
+    for i in 0..ships {
+        let layout = sectors.choose_mut(&mut rng).expect("there are sectors");
+        let owner = if i == 0 {
+            "Player"
+        } else {
+            *FACTION_NAMES.choose(&mut rng).expect("there are factions")
+        };
+        let position = (
+            rng.gen_range(MARGIN, ARENA_WIDTH - MARGIN),
+            rng.gen_range(MARGIN, ARENA_HEIGHT - MARGIN),
+        );
+
+        let class = SHIP_CLASSES.choose(&mut rng).expect("there are classes");
+
+        layout.def.ships.push(ShipDef {
+            class: (*class).to_owned(),
+            owner: owner.to_owned(),
+            position,
+            behaviour: BehaviourDef::Idle,
+            cargo: Vec::new(),
+            script: None,
+        });
+    }
+
+    // Put the player's ship first, such that it is followed.
+    let player = sectors
+        .iter()
+        .position(|layout| layout.def.ships.iter().any(|ship| ship.owner == "Player"));
+    if let Some(player) = player {
+        sectors.swap(0, player);
+    }
+
+    Scenario {
+        factions,
+        sectors: sectors.into_iter().map(|layout| layout.def).collect(),
+        jump_gates,
+        ..Scenario::default()
+    }
+}
+
+fn generate_sector(rng: &mut Pcg64Mcg, index: usize, count: usize) -> SectorLayout {
+    let name = match index / STAR_NAMES.len() {
+        0 => STAR_NAMES[index].to_owned(),
+        n => format!("{} {}", STAR_NAMES[index % STAR_NAMES.len()], n + 1),
+    };
+
+    // Spread the sectors over a circle on the galaxy map.
+    let angle = index as f32 / count as f32 * std::f32::consts::PI * 2.;
+    let position = (3. * angle.cos(), 3. * angle.sin());
+
+    let mut bodies = vec![BodyDef {
+        name: name.clone(),
+        kind: CelestialBody::Star,
+        radius: rng.gen_range(25., 45.),
+        location: LocationDef::At(STAR_POSITION.0, STAR_POSITION.1),
+    }];
+
+    let planet_count = rng.gen_range(1, PLANET_SUFFIXES.len() + 1);
+    let spacing = (MAX_PLANET_ORBIT - 150.) / planet_count as f32;
+    let planets: Vec<String> = PLANET_SUFFIXES[..planet_count]
+        .iter()
+        .enumerate()
+        .map(|(i, suffix)| {
+            let planet = format!("{} {}", name, suffix);
+            let radius = 150. + spacing * (i as f32 + rng.gen_range(0.5, 1.));
+            bodies.push(BodyDef {
+                name: planet.clone(),
+                kind: CelestialBody::Planet,
+                radius: rng.gen_range(15., 30.),
+                location: LocationDef::Orbit {
+                    center: name.clone(),
+                    radius,
+                    // Outer planets take longer to complete an orbit.
+                    period: (radius * 150.) as u32,
+                    phase: rng.gen_range(0., std::f32::consts::PI * 2.),
+                },
+            });
+            planet
+        })
+        .collect();
+
+    SectorLayout {
+        def: SectorDef {
+            name,
+            position,
+            bodies,
+            ..SectorDef::default()
+        },
+        planets,
+        occupied: Vec::new(),
+    }
+}
+
+/// Link the sectors in a ring, such that every sector can be reached from every other one.
+fn generate_jump_gates(rng: &mut Pcg64Mcg, sectors: &mut [SectorLayout]) -> Vec<JumpGateDef> {
+    let count = sectors.len();
+    let links: Vec<(usize, usize)> = match count {
+        0 | 1 => Vec::new(),
+        2 => vec![(0, 1)],
+        _ => (0..count).map(|i| (i, (i + 1) % count)).collect(),
+    };
+
+    links
+        .into_iter()
+        .map(|(a, b)| {
+            let mut end = |i: usize| {
+                let position = free_position(rng, &sectors[i].occupied);
+                sectors[i].occupied.push(position);
+                GateEndDef {
+                    sector: sectors[i].def.name.clone(),
+                    position,
+                }
+            };
+
+            JumpGateDef {
+                a: end(a),
+                b: end(b),
+            }
+        })
+        .collect()
+}
+
+/// Random position beyond the planets, away from anything occupied when possible.
+fn free_position(rng: &mut Pcg64Mcg, occupied: &[(f32, f32)]) -> (f32, f32) {
+    let distance = |(ax, ay): (f32, f32), (bx, by): (f32, f32)| f32::hypot(ax - bx, ay - by);
+
+    let mut candidate = (MARGIN, MARGIN);
+    for _ in 0..100 {
+        candidate = (
+            rng.gen_range(MARGIN, ARENA_WIDTH - MARGIN),
+            rng.gen_range(MARGIN, ARENA_HEIGHT - MARGIN),
+        );
+
+        if distance(candidate, STAR_POSITION) > MAX_PLANET_ORBIT + 80.
+            && occupied.iter().all(|&p| distance(candidate, p) > SPACING)
+        {
+            break;
+        }
+    }
+    candidate
+}
+
+fn clamp((x, y): (f32, f32)) -> (f32, f32) {
+    (
+        x.max(MARGIN).min(ARENA_WIDTH - MARGIN),
+        y.max(MARGIN).min(ARENA_HEIGHT - MARGIN),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripting::Scripts;
+    use crate::ships::ShipClasses;
+    use enum_map::EnumMap;
+
+    #[test]
+    fn recipes_produce_every_cargo_type() {
+        let mut produced = EnumMap::<CargoType, bool>::default();
+        for recipe in RECIPES {
+            for &(t, _) in recipe.products {
+                produced[t] = true;
+            }
+        }
+
+        assert!(produced.values().all(|&p| p));
+    }
+
+    #[test]
+    fn generated_scenario_is_valid() {
+        for seed in 0..20 {
+            generate(seed, &GeneratorConfig::default())
+                .validate(&ShipClasses::default(), &Scripts::default())
+                .unwrap();
+        }
+    }
+}
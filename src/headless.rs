@@ -0,0 +1,77 @@
+use amethyst::ecs::{join::Join, Entities, ReadStorage, World, WorldExt};
+use log::info;
+use std::time::Instant;
+
+use crate::setup::Setup;
+use traders::components::*;
+use traders::events::EventCounts;
+use traders::resources::*;
+use traders::simulation;
+
+/// Simulate the given number of ticks as fast as possible, without a window, and print the resulting state.
+pub fn run(setup: Setup, ticks: u32) -> amethyst::Result<()> {
+    let mut world = World::new();
+    let mut dispatcher = simulation::dispatcher(setup.profile.is_some()).build();
+    dispatcher.setup(&mut world);
+    simulation::register_components(&mut world);
+
+    world.insert(CurrentTime::default());
+    world.insert(SimRng::seed_from_u64(setup.config.seed));
+
+    setup.begin(&mut world)?;
+
+    let start = Instant::now();
+    for _ in 0..ticks {
+        setup.process_requests(&mut world);
+        simulation::tick(&mut dispatcher, &mut world);
+    }
+    let elapsed = start.elapsed();
+
+    info!(
+        "Simulated {} ticks in {:?} ({:.0} ticks/s).",
+        ticks,
+        elapsed,
+        f64::from(ticks) / elapsed.as_secs_f64()
+    );
+
+    setup.finish(&mut world)?;
+    info!("Events: {:?}.", *world.read_resource::<EventCounts>());
+    print_state(&world);
+    Ok(())
+}
+
+fn print_state(world: &World) {
+    let (entities, sector, pos, behaviour, cargo): (
+        Entities,
+        ReadStorage<Sector>,
+        ReadStorage<Position>,
+        ReadStorage<ShipBehaviour>,
+        ReadStorage<Cargo>,
+    ) = world.system_data();
+
+    println!("time: {}", *world.read_resource::<CurrentTime>().0);
+
+    for (e, sector, pos, behaviour, cargo) in (
+        &entities,
+        &sector,
+        &pos,
+        (&behaviour).maybe(),
+        (&cargo).maybe(),
+    )
+        .join()
+    {
+        print!("{:?} {:?} ({:.1}, {:.1})", e, sector, pos.x, pos.y);
+
+        if let Some(behaviour) = behaviour {
+            print!(" {:?}", behaviour);
+        }
+
+        if let Some(cargo) = cargo {
+            for (t, amount) in cargo.iter().filter(|&(_, amount)| *amount != 0) {
+                print!(" {:?}={}", t, *amount);
+            }
+        }
+
+        println!();
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Notices files which were created or modified in a set of directories, such that they can be reloaded.
+///
+/// Modification times are compared at most once per interval. Polling this rarely costs next to nothing, and suffices
+/// for files edited by hand.
+#[derive(Debug)]
+pub struct FileWatcher {
+    dirs: Vec<PathBuf>,
+    interval: Duration,
+    last_poll: Instant,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    /// Watch the files directly in the given directories, not those in subdirectories.
+    pub fn new(dirs: Vec<PathBuf>, interval: Duration) -> Self {
+        let modified = scan(&dirs);
+        Self {
+            dirs,
+            interval,
+            last_poll: Instant::now(),
+            modified,
+        }
+    }
+
+    /// Files which were created or modified since the last poll, ordered by path.
+    ///
+    /// Yields nothing when polled again within the interval.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let modified = scan(&self.dirs);
+        let mut changed: Vec<PathBuf> = modified
+            .iter()
+            .filter(|&(path, time)| self.modified.get(path) != Some(time))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.sort();
+
+        self.modified = modified;
+        changed
+    }
+}
+
+/// Modification time of every file in the directories. Those which cannot be read are left out.
+fn scan(dirs: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            Some((entry.path(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notices_new_files() {
+        let dir = std::env::temp_dir().join(format!("traders-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("old.ron"), "()").unwrap();
+
+        let mut watcher = FileWatcher::new(vec![dir.clone()], Duration::default());
+        assert!(watcher.poll().is_empty());
+
+        fs::write(dir.join("new.ron"), "()").unwrap();
+        assert_eq!(watcher.poll(), vec![dir.join("new.ron")]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
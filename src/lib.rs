@@ -0,0 +1,22 @@
+pub mod api;
+pub mod behaviour_tree;
+pub mod commands;
+pub mod components;
+pub mod events;
+pub mod factions;
+pub mod galaxy;
+pub mod generator;
+pub mod hot_reload;
+pub mod names;
+pub mod navigation;
+pub mod profiling;
+pub mod recipes;
+pub mod replay;
+pub mod resources;
+pub mod savegame;
+pub mod scenario;
+pub mod scripting;
+pub mod ships;
+pub mod simulation;
+pub mod statistics;
+pub mod systems;
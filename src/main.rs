@@ -1,14 +1,15 @@
-pub mod components;
 pub mod game;
-pub mod resources;
-pub mod systems;
+pub mod headless;
+pub mod presentation;
+pub mod setup;
 
 use amethyst::{
+    config::Config,
     core::{frame_limiter::FrameRateLimitStrategy, transform::TransformBundle},
     input::{InputBundle, StringBindings},
     prelude::*,
     renderer::{
-        plugins::{RenderFlat2D, RenderToWindow},
+        plugins::{RenderDebugLines, RenderFlat2D, RenderToWindow},
         types::DefaultBackend,
         RenderingBundle,
     },
@@ -17,38 +18,269 @@ use amethyst::{
 };
 
 use crate::game::Game;
+use crate::setup::{Setup, Start};
+use log::{error, warn};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use structopt::StructOpt;
+use traders::api::ApiServer;
+use traders::generator::{self, GeneratorConfig};
+use traders::recipes::Recipes;
+use traders::replay::{Recording, Session};
+use traders::resources::SimulationSpeed;
+use traders::savegame::SaveGame;
+use traders::scenario::Scenario;
+use traders::scripting::Scripts;
+use traders::ships::ShipClasses;
+use traders::simulation::SimulationConfig;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "traders")]
+struct Options {
+    /// Run the simulation without a window or GPU, as fast as possible.
+    #[structopt(long)]
+    headless: bool,
+    /// Number of ticks to simulate when running headless.
+    #[structopt(long, default_value = "3000")]
+    ticks: u32,
+    /// Seed for all randomness, overriding the one in `config/simulation.ron`.
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Speed to start the game at: paused, max, or the number of ticks per tick of real time, up to 10.
+    #[structopt(long, default_value = "1")]
+    speed: SimulationSpeed,
+    /// Scenario describing the initial universe, `assets/scenarios/default.ron` in the application root by default.
+    #[structopt(long, parse(from_os_str))]
+    scenario: Option<PathBuf>,
+    /// Generate a universe from the given seed, instead of reading the scenario.
+    #[structopt(long, conflicts_with = "scenario")]
+    generate: Option<u64>,
+    /// Write the scenario to the given file and exit, for example to tweak a generated universe by hand.
+    #[structopt(long, parse(from_os_str))]
+    write_scenario: Option<PathBuf>,
+    /// Continue from a saved game, instead of starting from a scenario.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["generate", "write-scenario", "record", "replay"]
+    )]
+    load: Option<PathBuf>,
+    /// Record the seed, scenario and all player commands to the given file.
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+    /// Replay a recording, ignoring the scenario and seed otherwise chosen.
+    #[structopt(long, parse(from_os_str), conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    /// Write the statistics of the economy to the given file when exiting, as JSON if it ends in `.json` and as CSV
+    /// otherwise.
+    #[structopt(long, parse(from_os_str))]
+    stats: Option<PathBuf>,
+    /// Write the time spent per system and the number of entities to the given file when exiting.
+    #[structopt(long, parse(from_os_str))]
+    profile: Option<PathBuf>,
+    /// Serve the control API on the given loopback address, for example `127.0.0.1:7878`.
+    #[structopt(long)]
+    api: Option<String>,
+    /// Ticks between state hashes stored in a recording, to detect replays diverging. Zero disables them.
+    #[structopt(long, default_value = "500")]
+    hash_interval: u32,
+}
+
+impl Options {
+    /// Resolve the files named on the command line against the working directory, as shells complete them.
+    fn resolve_paths(&mut self, cwd: &Path) {
+        let paths = vec![
+            &mut self.scenario,
+            &mut self.write_scenario,
+            &mut self.load,
+            &mut self.record,
+            &mut self.replay,
+            &mut self.stats,
+            &mut self.profile,
+        ];
+        for path in paths.into_iter().flatten() {
+            *path = cwd.join(&*path);
+        }
+    }
+
+    fn scenario_path(&self, app_root: &Path) -> PathBuf {
+        self.scenario.clone().unwrap_or_else(|| {
+            app_root
+                .join("assets")
+                .join("scenarios")
+                .join("default.ron")
+        })
+    }
+}
+
+/// Read or generate the scenario, exiting when it is invalid.
+fn scenario(
+    options: &Options,
+    app_root: &Path,
+    classes: &ShipClasses,
+    scripts: &Scripts,
+) -> Scenario {
+    let scenario = match options.generate {
+        Some(seed) => {
+            let scenario = generator::generate(seed, &GeneratorConfig::default());
+            scenario.validate(classes, scripts).map(|_| scenario)
+        }
+        None => Scenario::load(&options.scenario_path(app_root), classes, scripts),
+    };
+
+    match scenario {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            error!("Invalid scenario: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
 fn main() -> amethyst::Result<()> {
+    let mut options = Options::from_args();
+    options.resolve_paths(&std::env::current_dir()?);
     amethyst::start_logger(Default::default());
 
     let app_root = application_root_dir()?;
     let display_config_path = app_root.join("config").join("display.ron");
+    let bindings_path = app_root.join("config").join("bindings.ron");
+    let mut config = SimulationConfig::load(app_root.join("config").join("simulation.ron"))?;
+    if let Some(seed) = options.seed {
+        config.seed = seed;
+    }
+
+    let ship_classes_path = app_root.join("assets").join("ships.ron");
+    let ship_classes = ShipClasses::load(&ship_classes_path).unwrap_or_else(|e| {
+        warn!(
+            "Could not read ship classes, using the built-in ones: {}.",
+            e
+        );
+        ShipClasses::default()
+    });
+
+    let scripts_dir = app_root.join("assets").join("scripts");
+    let scripts = Scripts::load_dir(&scripts_dir).unwrap_or_else(|e| {
+        warn!("Could not read scripts from {:?}: {}.", scripts_dir, e);
+        Scripts::default()
+    });
+
+    let (start, session, ticks) = match (&options.load, &options.replay, &options.record) {
+        (Some(path), ..) => {
+            let save = SaveGame::load(path)?;
+            (Start::Save(save), Session::Live, options.ticks)
+        }
+        (None, Some(path), _) => {
+            let recording = Recording::load(path)?;
+            config.seed = recording.seed;
+            let ticks = *recording.duration;
+            (
+                Start::Scenario(recording.scenario.clone()),
+                Session::Replay(recording),
+                ticks,
+            )
+        }
+        (None, None, Some(path)) => (
+            Start::Scenario(scenario(&options, &app_root, &ship_classes, &scripts)),
+            Session::Record {
+                path: path.clone(),
+                hash_interval: Some(options.hash_interval),
+            },
+            options.ticks,
+        ),
+        (None, None, None) => (
+            Start::Scenario(scenario(&options, &app_root, &ship_classes, &scripts)),
+            Session::Live,
+            options.ticks,
+        ),
+    };
+
+    if let (Some(path), Start::Scenario(scenario)) = (&options.write_scenario, &start) {
+        scenario.save(path)?;
+        return Ok(());
+    }
+
+    // A replay runs by the rules it was recorded with, whatever the data files say now.
+    let (recipes, ship_classes) = match &session {
+        Session::Replay(recording) => (
+            Recipes::from_defs(&recording.recipes)?,
+            recording.ship_classes.clone(),
+        ),
+        _ => {
+            let recipes_path = app_root.join("assets").join("recipes.ron");
+            let recipes = Recipes::load(&recipes_path).unwrap_or_else(|e| {
+                warn!("Could not read recipes, using the built-in ones: {}.", e);
+                Recipes::default()
+            });
+            (recipes, ship_classes)
+        }
+    };
+
+    // Only a scenario read from a file can be reloaded when the file changes.
+    let scenario_path = match (&start, options.generate, &options.replay) {
+        (Start::Scenario(_), None, None) => Some(options.scenario_path(&app_root)),
+        _ => None,
+    };
+
+    let setup = Setup {
+        config,
+        start,
+        scenario_path,
+        session,
+        scripts,
+        recipes,
+        ship_classes,
+        stats: options.stats,
+        profile: options.profile,
+        api: match &options.api {
+            Some(addr) => Some(ApiServer::start(addr.as_str())?),
+            None => None,
+        },
+    };
+
+    if options.headless {
+        return headless::run(setup, ticks);
+    }
 
     let game_data = GameDataBuilder::default()
-        .with(systems::IncrementTime, "increment_time", &[])
-        .with(systems::behaviour::Idle, "behaviour_idle", &[])
-        .with(systems::behaviour::FlyTo, "behaviour_fly_to", &[])
-        .with(systems::Movement, "movement", &["behaviour_fly_to"])
-        .with(systems::Rotation, "rotation", &["behaviour_fly_to"])
         .with(
-            systems::DerivePositionalTransform,
+            presentation::DerivePositionalTransform,
             "derive_positional_transform",
-            &["movement"],
+            &[],
         )
         .with(
-            systems::DeriveRotationalTransform,
+            presentation::DeriveRotationalTransform,
             "derive_rotational_transform",
-            &["rotation"],
+            &[],
+        )
+        .with(
+            presentation::DrawCelestialBodies,
+            "draw_celestial_bodies",
+            &[],
         )
-        .with(systems::CameraControl, "camera_control", &["movement"])
+        .with(presentation::DrawJumpGates, "draw_jump_gates", &[])
         .with(
-            systems::UiRelativePositioning,
+            presentation::CameraControl,
+            "camera_control",
+            &["derive_positional_transform"],
+        )
+        .with(
+            presentation::UiRelativePositioning,
             "ui_relative_positioning",
-            &["movement"],
+            &["derive_positional_transform"],
+        )
+        .with(
+            presentation::SectorVisibility,
+            "sector_visibility",
+            &["camera_control"],
+        )
+        .with(
+            presentation::ShowNotifications::default(),
+            "show_notifications",
+            &[],
         )
         .with_bundle(TransformBundle::new())?
-        .with_bundle(InputBundle::<StringBindings>::new())?
+        .with_bundle(InputBundle::<StringBindings>::new().with_bindings_from_file(bindings_path)?)?
         .with_bundle(UiBundle::<StringBindings>::new())?
         // .with_system_desc(UiEventHandlerSystemDesc::default(), "ui_event_handler", &[])
         .with_bundle(
@@ -58,11 +290,12 @@ fn main() -> amethyst::Result<()> {
                         .with_clear([0.0, 0.0, 0.0, 1.0]),
                 )
                 .with_plugin(RenderFlat2D::default())
+                .with_plugin(RenderDebugLines::default())
                 .with_plugin(RenderUi::default()),
         )?;
 
     let assets_dir = app_root.join("assets");
-    let mut game = Application::build(assets_dir, Game)?
+    let mut game = Application::build(assets_dir, Game::new(setup, options.speed))?
         .with_frame_limit(
             FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(10)),
             50,
@@ -0,0 +1,157 @@
+use amethyst::ecs::Entity;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_pcg::Pcg64Mcg;
+use std::collections::HashSet;
+
+use crate::components::Name;
+
+const SHIP_ADJECTIVES: &[&str] = &[
+    "Amber",
+    "Bright",
+    "Crimson",
+    "Distant",
+    "Golden",
+    "Iron",
+    "Lucky",
+    "Northern",
+    "Patient",
+    "Quiet",
+    "Restless",
+    "Silent",
+    "Solar",
+    "Stubborn",
+    "Swift",
+    "Wandering",
+];
+
+const SHIP_NOUNS: &[&str] = &[
+    "Beacon",
+    "Comet",
+    "Drifter",
+    "Falcon",
+    "Harbinger",
+    "Heron",
+    "Lantern",
+    "Marlin",
+    "Meridian",
+    "Mule",
+    "Nomad",
+    "Otter",
+    "Pilgrim",
+    "Sparrow",
+    "Tortoise",
+    "Venture",
+];
+
+/// Kind of facility a station is, after the recipe it fabricates.
+fn facility(recipe: Option<&str>) -> &'static str {
+    match recipe {
+        Some("carbon_ore") => "Carbon Mine",
+        Some("metal_ore") => "Metal Mine",
+        Some("ice_ore") => "Ice Mine",
+        Some("carbon") => "Carbon Refinery",
+        Some("metal") => "Smelter",
+        Some("water") => "Waterworks",
+        Some("oxygen") => "Oxygen Plant",
+        Some("fuel") => "Fuel Refinery",
+        Some("food") => "Farm",
+        Some("recycling") => "Recycler",
+        Some("electronics") => "Electronics Fab",
+        Some("robotics") => "Robotics Works",
+        Some("hullplating") => "Plating Mill",
+        Some(_) => "Factory",
+        None => "Depot",
+    }
+}
+
+/// Seeded generator of names for ships and stations, which are unique within a universe.
+///
+/// It draws from random numbers of its own rather than from `SimRng`, such that naming never affects the simulation.
+#[derive(Debug, Clone)]
+pub struct NameGenerator {
+    rng: Pcg64Mcg,
+    used: HashSet<String>,
+}
+
+impl Default for NameGenerator {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl NameGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Pcg64Mcg::seed_from_u64(seed),
+            used: HashSet::new(),
+        }
+    }
+
+    /// Keep the given name, chosen by hand, from being generated.
+    pub fn reserve(&mut self, name: &str) {
+        self.used.insert(name.to_owned());
+    }
+
+    /// Name of a ship, such as `Silent Heron`.
+    pub fn ship(&mut self) -> String {
+        let adjective = SHIP_ADJECTIVES
+            .choose(&mut self.rng)
+            .expect("list is not empty");
+        let noun = SHIP_NOUNS.choose(&mut self.rng).expect("list is not empty");
+        self.unique(format!("{} {}", adjective, noun))
+    }
+
+    /// Name of a station after where it is and what it fabricates, such as `Sol b Fuel Refinery`.
+    pub fn station(&mut self, location: &str, recipe: Option<&str>) -> String {
+        self.unique(format!("{} {}", location, facility(recipe)))
+    }
+
+    fn unique(&mut self, name: String) -> String {
+        if self.used.insert(name.clone()) {
+            return name;
+        }
+
+        (2..)
+            .map(|i| format!("{} {}", name, i))
+            .find(|candidate| self.used.insert(candidate.clone()))
+            .expect("some number is unused")
+    }
+}
+
+/// Entity as shown in logs, such as `Silent Heron (#12)`, by id alone when it has no name.
+pub fn describe(entity: Entity, name: Option<&Name>) -> String {
+    match name {
+        Some(name) => format!("{} (#{})", name.0, entity.id()),
+        None => format!("#{}", entity.id()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_yields_same_names() {
+        let names = |seed| {
+            let mut generator = NameGenerator::new(seed);
+            (0..10).map(|_| generator.ship()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(7), names(7));
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut generator = NameGenerator::new(0);
+        assert_eq!(generator.station("Sol", Some("fuel")), "Sol Fuel Refinery");
+        assert_eq!(
+            generator.station("Sol", Some("fuel")),
+            "Sol Fuel Refinery 2"
+        );
+        assert_eq!(generator.station("Vega", None), "Vega Depot");
+
+        let ships: HashSet<String> = (0..1000).map(|_| generator.ship()).collect();
+        assert_eq!(ships.len(), 1000);
+    }
+}
@@ -0,0 +1,369 @@
+use amethyst::ecs::Entity;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::components::{Point2, Sector, ARENA_HEIGHT, ARENA_WIDTH};
+
+/// Number of waypoints placed around each obstacle.
+const CORNERS: usize = 8;
+
+/// Additional distance ships keep from obstacles when passing them.
+const CLEARANCE: f32 = 10.;
+
+/// Size of the grid cells used to share cached paths between nearby ships.
+const CACHE_CELL: f32 = 50.;
+
+/// Margin added around orbiting obstacles, such that a graph stays valid until one of them has moved further.
+const DRIFT: f32 = 20.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Point2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn contains(&self, p: &Point2) -> bool {
+        nalgebra_glm::length(&(p - self.center)) < self.radius
+    }
+
+    /// Whether the line segment from a to b passes through this circle.
+    pub fn intersects(&self, a: &Point2, b: &Point2) -> bool {
+        let ab = b - a;
+        let len_sq = ab.dot(&ab);
+        let t = if len_sq > 0. {
+            f32::min(1., f32::max(0., (self.center - a).dot(&ab) / len_sq))
+        } else {
+            0.
+        };
+        let closest = a + ab * t;
+        nalgebra_glm::length(&(self.center - closest)) < self.radius
+    }
+}
+
+fn within_arena(p: &Point2) -> bool {
+    p.x >= 0. && p.x <= ARENA_WIDTH && p.y >= 0. && p.y <= ARENA_HEIGHT
+}
+
+/// Visibility graph over the waypoints surrounding all obstacles, as they were when it was built.
+#[derive(Debug, Default)]
+pub struct NavigationGraph {
+    obstacles: Vec<(Entity, Circle)>,
+    nodes: Vec<Point2>,
+    edges: Vec<Vec<(usize, f32)>>,
+}
+
+impl NavigationGraph {
+    pub fn new(obstacles: Vec<(Entity, Circle)>) -> Self {
+        // Place the corners far enough out that the polygon edges between them stay clear.
+        let spread = std::f32::consts::PI / CORNERS as f32;
+        let mut nodes = Vec::new();
+        for (_, circle) in obstacles.iter() {
+            let distance = (circle.radius + CLEARANCE) / spread.cos();
+            for i in 0..CORNERS {
+                let angle = 2. * spread * i as f32;
+                let node =
+                    circle.center + nalgebra_glm::vec2(angle.cos(), angle.sin()).scale(distance);
+
+                if within_arena(&node) && !obstacles.iter().any(|(_, c)| c.contains(&node)) {
+                    nodes.push(node);
+                }
+            }
+        }
+
+        let mut res = Self {
+            obstacles,
+            nodes,
+            edges: Vec::new(),
+        };
+
+        res.edges = (0..res.nodes.len())
+            .map(|i| {
+                (0..res.nodes.len())
+                    .filter(|&j| i != j && res.is_clear(&res.nodes[i], &res.nodes[j], None))
+                    .map(|j| (j, nalgebra_glm::length(&(res.nodes[j] - res.nodes[i]))))
+                    .collect()
+            })
+            .collect();
+
+        res
+    }
+
+    pub fn obstacles(&self) -> &[(Entity, Circle)] {
+        &self.obstacles
+    }
+
+    /// Whether a ship can fly from a to b in a straight line.
+    ///
+    /// Obstacles containing either end are ignored, such that ships can leave and approach stations, as is the
+    /// obstacle of the entity being approached, which may have moved on since the graph was built.
+    pub fn is_clear(&self, a: &Point2, b: &Point2, target: Option<Entity>) -> bool {
+        !self.obstacles.iter().any(|&(e, c)| {
+            Some(e) != target && !c.contains(a) && !c.contains(b) && c.intersects(a, b)
+        })
+    }
+
+    /// Compute the waypoints to fly from `from` to `to`, where the target is, using A*, excluding `from` itself.
+    ///
+    /// Yields None when `to` is unreachable.
+    pub fn find_path(&self, from: &Point2, target: Entity, to: &Point2) -> Option<Vec<Point2>> {
+        if self.is_clear(from, to, Some(target)) {
+            return Some(vec![*to]);
+        }
+
+        // The start and goal are appended as the two final nodes.
+        let start = self.nodes.len();
+        let goal = start + 1;
+        let point = |i: usize| match i {
+            i if i == start => *from,
+            i if i == goal => *to,
+            i => self.nodes[i],
+        };
+        let heuristic = |i: usize| nalgebra_glm::length(&(to - point(i)));
+
+        let goal_edges: Vec<bool> = self
+            .nodes
+            .iter()
+            .map(|n| self.is_clear(n, to, Some(target)))
+            .collect();
+        let neighbours = |i: usize| -> Vec<(usize, f32)> {
+            let mut res: Vec<(usize, f32)> = if i == start {
+                (0..self.nodes.len())
+                    .filter(|&j| self.is_clear(from, &self.nodes[j], None))
+                    .map(|j| (j, nalgebra_glm::length(&(self.nodes[j] - from))))
+                    .collect()
+            } else {
+                self.edges[i].clone()
+            };
+            if i != start && goal_edges[i] {
+                res.push((goal, nalgebra_glm::length(&(to - self.nodes[i]))));
+            }
+            res
+        };
+
+        let mut cost: HashMap<usize, f32> = HashMap::new();
+        let mut came_from: HashMap<usize, usize> = HashMap::new();
+        let mut open = BinaryHeap::new();
+
+        cost.insert(start, 0.);
+        open.push(Candidate {
+            estimate: heuristic(start),
+            node: start,
+        });
+
+        while let Some(Candidate { node, .. }) = open.pop() {
+            if node == goal {
+                let mut res = vec![*to];
+                let mut current = goal;
+                while let Some(&previous) = came_from.get(&current) {
+                    if previous != start {
+                        res.push(point(previous));
+                    }
+                    current = previous;
+                }
+                res.reverse();
+                return Some(res);
+            }
+
+            let node_cost = cost[&node];
+            for (next, distance) in neighbours(node) {
+                let next_cost = node_cost + distance;
+                if cost.get(&next).map_or(true, |&c| next_cost < c) {
+                    cost.insert(next, next_cost);
+                    came_from.insert(next, node);
+                    open.push(Candidate {
+                        estimate: next_cost + heuristic(next),
+                        node: next,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Candidate {
+    estimate: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    // Reversed such that the BinaryHeap yields the lowest estimate first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Obstacles of a sector, split by whether they orbit.
+#[derive(Debug, Default, PartialEq)]
+struct SectorObstacles {
+    fixed: Vec<(Entity, Circle)>,
+    orbiting: Vec<(Entity, Circle)>,
+}
+
+impl SectorObstacles {
+    /// Whether a graph built for these obstacles is still valid for the current ones.
+    fn covers(&self, current: &SectorObstacles) -> bool {
+        self.fixed == current.fixed
+            && self.orbiting.len() == current.orbiting.len()
+            && self
+                .orbiting
+                .iter()
+                .zip(&current.orbiting)
+                .all(|(&(e, built), &(f, now))| {
+                    e == f
+                        && built.radius == now.radius
+                        && nalgebra_glm::length(&(now.center - built.center)) <= DRIFT
+                })
+    }
+
+    fn graph(&self) -> NavigationGraph {
+        let orbiting = self.orbiting.iter().map(|&(e, circle)| {
+            let widened = Circle {
+                center: circle.center,
+                radius: circle.radius + DRIFT,
+            };
+            (e, widened)
+        });
+        NavigationGraph::new(self.fixed.iter().copied().chain(orbiting).collect())
+    }
+}
+
+/// Navigation graph per sector and the paths computed on them, rebuilt whenever the obstacles change.
+///
+/// Orbiting obstacles are widened by `DRIFT`, such that graphs are only rebuilt once in a while as they move.
+#[derive(Debug, Default)]
+pub struct Navigation {
+    graphs: HashMap<Sector, (SectorObstacles, NavigationGraph)>,
+    paths: HashMap<(Sector, i32, i32, Entity), Option<Vec<Point2>>>,
+}
+
+impl Navigation {
+    pub fn update(
+        &mut self,
+        fixed: Vec<(Entity, Sector, Circle)>,
+        orbiting: Vec<(Entity, Sector, Circle)>,
+    ) {
+        let mut per_sector: HashMap<Sector, SectorObstacles> = HashMap::new();
+        for (e, sector, circle) in fixed {
+            per_sector
+                .entry(sector)
+                .or_default()
+                .fixed
+                .push((e, circle));
+        }
+        for (e, sector, circle) in orbiting {
+            per_sector
+                .entry(sector)
+                .or_default()
+                .orbiting
+                .push((e, circle));
+        }
+
+        let paths = &mut self.paths;
+        self.graphs.retain(|sector, _| {
+            let keep = per_sector.contains_key(sector);
+            if !keep {
+                paths.retain(|(s, ..), _| s != sector);
+            }
+            keep
+        });
+
+        for (sector, obstacles) in per_sector {
+            let changed = self
+                .graphs
+                .get(&sector)
+                .map_or(true, |(built, _)| !built.covers(&obstacles));
+
+            if changed {
+                let graph = obstacles.graph();
+                self.graphs.insert(sector, (obstacles, graph));
+                self.paths.retain(|(s, ..), _| *s != sector);
+            }
+        }
+    }
+
+    /// Whether a ship can fly from a to b in a straight line, ignoring the target it is approaching.
+    pub fn is_clear(&self, sector: Sector, a: &Point2, b: &Point2, target: Entity) -> bool {
+        self.graphs
+            .get(&sector)
+            .map_or(true, |(_, graph)| graph.is_clear(a, b, Some(target)))
+    }
+
+    /// Find a path towards the target entity, reusing paths found earlier from nearby.
+    pub fn find_path(
+        &mut self,
+        sector: Sector,
+        from: &Point2,
+        target: Entity,
+        to: &Point2,
+    ) -> Option<Vec<Point2>> {
+        let graph = match self.graphs.get(&sector) {
+            Some((_, graph)) => graph,
+            None => return Some(vec![*to]),
+        };
+
+        let cell = (
+            sector,
+            (from.x / CACHE_CELL).floor() as i32,
+            (from.y / CACHE_CELL).floor() as i32,
+            target,
+        );
+
+        // A path cached for a different spot in the cell is only useful when its first waypoint is in sight.
+        match self.paths.get(&cell) {
+            Some(None) => return None,
+            Some(Some(path))
+                if path
+                    .first()
+                    .map_or(false, |p| graph.is_clear(from, p, Some(target))) =>
+            {
+                return Some(path.clone());
+            }
+            _ => (),
+        }
+
+        let path = graph.find_path(from, target, to);
+        self.paths.insert(cell, path.clone());
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::{Builder, World, WorldExt};
+
+    #[test]
+    fn orbiting_obstacles_block_where_they_are() {
+        let mut world = World::new();
+        let (planet, station) = (world.create_entity().build(), world.create_entity().build());
+        let (from, to) = (Point2::new(100., 100.), Point2::new(500., 100.));
+        let at = |x, y| Circle {
+            center: Point2::new(x, y),
+            radius: 30.,
+        };
+
+        let mut navigation = Navigation::default();
+        navigation.update(vec![], vec![(planet, Sector(0), at(300., 100.))]);
+        assert!(!navigation.is_clear(Sector(0), &from, &to, station));
+        assert!(navigation.is_clear(Sector(0), &from, &to, planet));
+
+        navigation.update(vec![], vec![(planet, Sector(0), at(300., 300.))]);
+        assert!(navigation.is_clear(Sector(0), &from, &to, station));
+    }
+}
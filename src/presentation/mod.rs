@@ -0,0 +1,187 @@
+pub mod systems;
+pub mod ui;
+
+pub use systems::*;
+pub use ui::*;
+
+use amethyst::{
+    assets::Handle,
+    core::transform::Transform,
+    ecs::{join::Join, Entities, Entity, Read, ReadStorage, World, WorldExt, WriteStorage},
+    renderer::{SpriteRender, SpriteSheet},
+};
+use std::collections::HashSet;
+
+use traders::components::*;
+use traders::ships::ShipClasses;
+
+pub type Parent = amethyst::core::transform::Parent;
+pub type ParentHierarchy = amethyst::core::transform::ParentHierarchy;
+
+/// Sprite of ships whose class is unknown.
+const TRADER_SPRITE: usize = 0;
+const STATION_SPRITE: usize = 1;
+
+#[derive(Debug)]
+pub enum CameraBehaviour {
+    Static,
+    Pan(Translation2),
+    Follow(Entity),
+}
+
+#[derive(Debug)]
+pub struct CameraState {
+    pub zoom: f32,
+    pub sector: Sector,
+    pub behaviour: CameraBehaviour,
+}
+
+#[derive(Debug, Default)]
+pub struct Selection(pub Option<Entity>);
+
+#[derive(Debug, Default)]
+pub struct HoverSelectable(pub HashSet<Entity>);
+
+fn compute_name(target: &Entity, name: &str) -> String {
+    format!("{}-{}-{}", name, target.gen().id(), target.id())
+}
+
+/// Attach sprites, transforms and labels to all simulated entities which do not have them yet.
+///
+/// Labels show the name of the entity, or its kind when it has none.
+pub fn decorate(world: &mut World) {
+    let undecorated: Vec<(Entity, &'static str, Option<String>, Option<usize>, bool)> = {
+        let (entities, classes, name, station, trader, stats, body, gate, field, transform): (
+            Entities,
+            Option<Read<ShipClasses>>,
+            ReadStorage<Name>,
+            ReadStorage<Station>,
+            ReadStorage<Trader>,
+            ReadStorage<ShipStats>,
+            ReadStorage<CelestialBody>,
+            ReadStorage<JumpGate>,
+            ReadStorage<AsteroidField>,
+            ReadStorage<Transform>,
+        ) = world.system_data();
+
+        (
+            &entities,
+            (&name).maybe(),
+            (&station).maybe(),
+            (&trader).maybe(),
+            (&stats).maybe(),
+            (&body).maybe(),
+            (&gate).maybe(),
+            (&field).maybe(),
+            !&transform,
+        )
+            .join()
+            .filter_map(|(e, name, station, trader, stats, body, gate, field, _)| {
+                let (kind, sprite_number, selectable) = match (station, trader, body, gate, field) {
+                    (Some(_), ..) => ("station", Some(STATION_SPRITE), true),
+                    (_, Some(_), ..) => {
+                        ("trader", Some(ship_sprite(classes.as_deref(), stats)), true)
+                    }
+                    (_, _, Some(CelestialBody::Star), ..) => ("star", None, false),
+                    (_, _, Some(CelestialBody::Planet), ..) => ("planet", None, false),
+                    (_, _, _, Some(_), _) => ("gate", None, true),
+                    (.., Some(_)) => ("asteroids", None, false),
+                    _ => return None,
+                };
+                let label = name.map(|name| name.0.clone());
+                Some((e, kind, label, sprite_number, selectable))
+            })
+            .collect()
+    };
+
+    for (e, kind, label, sprite_number, selectable) in undecorated {
+        let label = label.unwrap_or_else(|| kind.to_owned());
+        attach(world, e, kind, label, sprite_number, selectable);
+    }
+}
+
+/// Sprite of a ship after its class.
+fn ship_sprite(classes: Option<&ShipClasses>, stats: Option<&ShipStats>) -> usize {
+    match (classes, stats) {
+        (Some(classes), Some(stats)) => classes
+            .get(&stats.class)
+            .map_or(TRADER_SPRITE, |class| class.sprite),
+        _ => TRADER_SPRITE,
+    }
+}
+
+/// Show all ships with the current sprite of their class, after the catalogue was reloaded.
+pub fn update_ship_sprites(world: &mut World) {
+    let (classes, stats, mut sprite): (
+        Option<Read<ShipClasses>>,
+        ReadStorage<ShipStats>,
+        WriteStorage<SpriteRender>,
+    ) = world.system_data();
+
+    for (stats, sprite) in (&stats, &mut sprite).join() {
+        sprite.sprite_number = ship_sprite(classes.as_deref(), Some(stats));
+    }
+}
+
+/// Delete all simulated entities, together with their labels.
+pub fn clear(world: &mut World) {
+    let stale: Vec<Entity> = {
+        let (entities, sector, relative, parent): (
+            Entities,
+            ReadStorage<Sector>,
+            ReadStorage<UiRelative>,
+            ReadStorage<Parent>,
+        ) = world.system_data();
+
+        let anchors: HashSet<Entity> = (&entities, &relative).join().map(|(e, _)| e).collect();
+        let labels = (&entities, &parent)
+            .join()
+            .filter(|(_, parent)| anchors.contains(&parent.entity))
+            .map(|(e, _)| e);
+
+        (&entities, &sector)
+            .join()
+            .map(|(e, _)| e)
+            .chain(anchors.iter().copied())
+            .chain(labels)
+            .collect()
+    };
+
+    world
+        .delete_entities(&stale)
+        .expect("entities were just collected");
+    world.fetch_mut::<HoverSelectable>().0.clear();
+    world.fetch_mut::<Selection>().0 = None;
+}
+
+fn attach(
+    world: &mut World,
+    entity: Entity,
+    kind: &str,
+    label: String,
+    sprite_number: Option<usize>,
+    selectable: bool,
+) {
+    world
+        .write_storage::<Transform>()
+        .insert(entity, Transform::default())
+        .expect("entity is alive");
+
+    if let Some(sprite_number) = sprite_number {
+        let sprite_sheet = (*world.fetch::<Handle<SpriteSheet>>()).clone();
+        world
+            .write_storage::<SpriteRender>()
+            .insert(
+                entity,
+                SpriteRender {
+                    sprite_sheet,
+                    sprite_number,
+                },
+            )
+            .expect("entity is alive");
+    }
+
+    // The anchor keeps the entity in its id, such as `trader-0-12`, for debugging.
+    let anchor = create_ui_anchor(world, &entity, kind, selectable);
+    create_ui_label(world, &anchor, label);
+}
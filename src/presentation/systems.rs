@@ -0,0 +1,361 @@
+use amethyst::{
+    core::{math, transform::Transform, HiddenPropagate},
+    ecs::{
+        join::Join,
+        prelude::{
+            Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteExpect, WriteStorage,
+        },
+        Entities, Entity, World,
+    },
+    renderer::{debug_drawing::DebugLines, palette::Srgba, Camera},
+    shrev::{EventChannel, ReaderId},
+    ui::{UiText, UiTransform},
+    window::ScreenDimensions,
+};
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+use traders::components::*;
+use traders::events::SimEvent;
+use traders::galaxy::Galaxy;
+
+use super::*;
+
+pub struct DerivePositionalTransform;
+
+impl<'a> System<'a> for DerivePositionalTransform {
+    type SystemData = (
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Trader>,
+        ReadStorage<'a, Station>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (pos, trader, station, mut transform): Self::SystemData) {
+        for (pos, trader, station, transform) in
+            (&pos, (&trader).maybe(), (&station).maybe(), &mut transform).join()
+        {
+            transform.set_scale([1.0, 1.0, 1.0].into());
+
+            // Vary z-level based on type
+            let z = if trader.is_some() {
+                0.2
+            } else if station.is_some() {
+                0.1
+            } else {
+                0.0
+            };
+
+            transform.set_translation_xyz(pos.x.into(), pos.y.into(), z);
+        }
+    }
+}
+
+pub struct DeriveRotationalTransform;
+
+impl<'a> System<'a> for DeriveRotationalTransform {
+    type SystemData = (ReadStorage<'a, Angle>, WriteStorage<'a, Transform>);
+
+    fn run(&mut self, (angle, mut transform): Self::SystemData) {
+        for (angle, transform) in (&angle, &mut transform).join() {
+            transform.set_rotation_2d(*angle.deref());
+        }
+    }
+}
+
+pub struct DrawCelestialBodies;
+
+impl<'a> System<'a> for DrawCelestialBodies {
+    type SystemData = (
+        ReadExpect<'a, CameraState>,
+        ReadStorage<'a, Sector>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Obstacle>,
+        ReadStorage<'a, CelestialBody>,
+        ReadStorage<'a, AsteroidField>,
+        Write<'a, DebugLines>,
+    );
+
+    fn run(
+        &mut self,
+        (camera_state, sector, pos, obstacle, body, field, mut debug_lines): Self::SystemData,
+    ) {
+        for (_, pos, obstacle, body) in (&sector, &pos, &obstacle, &body)
+            .join()
+            .filter(|(&sector, ..)| sector == camera_state.sector)
+        {
+            let color = match body {
+                CelestialBody::Star => Srgba::new(1.0, 0.8, 0.2, 1.0),
+                CelestialBody::Planet => Srgba::new(0.3, 0.5, 1.0, 1.0),
+            };
+
+            debug_lines.draw_circle(
+                math::Point3::new(pos.x, pos.y, 0.05),
+                *obstacle.deref(),
+                32,
+                color,
+            );
+        }
+
+        for (_, pos, field) in (&sector, &pos, &field)
+            .join()
+            .filter(|(&sector, ..)| sector == camera_state.sector)
+        {
+            debug_lines.draw_circle(
+                math::Point3::new(pos.x, pos.y, 0.05),
+                field.radius,
+                24,
+                Srgba::new(0.6, 0.5, 0.4, 1.0),
+            );
+        }
+    }
+}
+
+pub struct DrawJumpGates;
+
+impl<'a> System<'a> for DrawJumpGates {
+    type SystemData = (
+        ReadExpect<'a, CameraState>,
+        ReadStorage<'a, Sector>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Hitbox>,
+        ReadStorage<'a, JumpGate>,
+        Write<'a, DebugLines>,
+    );
+
+    fn run(
+        &mut self,
+        (camera_state, sector, pos, hitbox, gate, mut debug_lines): Self::SystemData,
+    ) {
+        for (_, pos, hitbox, _) in (&sector, &pos, &hitbox, &gate)
+            .join()
+            .filter(|(&sector, ..)| sector == camera_state.sector)
+        {
+            debug_lines.draw_circle(
+                math::Point3::new(pos.x, pos.y, 0.05),
+                hitbox.x / 2.,
+                6,
+                Srgba::new(0.7, 0.3, 1.0, 1.0),
+            );
+        }
+    }
+}
+
+/// Hide everything outside of the sector currently in view.
+pub struct SectorVisibility;
+
+impl<'a> System<'a> for SectorVisibility {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CameraState>,
+        ReadStorage<'a, Sector>,
+        ReadStorage<'a, UiRelative>,
+        WriteStorage<'a, HiddenPropagate>,
+    );
+
+    fn run(&mut self, (entities, camera_state, sector, ui_relative, mut hidden): Self::SystemData) {
+        for (e, ui_relative) in (&entities, (&ui_relative).maybe()).join() {
+            // Ui anchors follow the entity they belong to.
+            let owner = match ui_relative {
+                Some(UiRelative(parent)) => *parent,
+                None => e,
+            };
+
+            let is_visible = match sector.get(owner) {
+                Some(&s) => s == camera_state.sector,
+                None => continue,
+            };
+
+            if is_visible {
+                hidden.remove(e);
+            } else if !hidden.contains(e) {
+                hidden
+                    .insert(e, HiddenPropagate::new())
+                    .expect("entity is alive");
+            }
+        }
+    }
+}
+
+pub struct CameraControl;
+
+impl<'a> System<'a> for CameraControl {
+    type SystemData = (
+        WriteExpect<'a, CameraState>,
+        ReadExpect<'a, ScreenDimensions>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Sector>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (mut camera_state, size, pos, sector, mut camera, mut transform): Self::SystemData,
+    ) {
+        let zoom = camera_state.zoom;
+
+        // Keep the followed entity in view as it jumps between sectors.
+        if let CameraBehaviour::Follow(target) = camera_state.behaviour {
+            if let Some(&s) = sector.get(target) {
+                camera_state.sector = s;
+            }
+        }
+
+        for (camera, transform) in (&mut camera, &mut transform).join() {
+            // Update the camera size per zoom level.
+            *camera = Camera::standard_2d(size.width() / zoom, size.height() / zoom);
+
+            match &camera_state.behaviour {
+                CameraBehaviour::Static => (),
+                CameraBehaviour::Follow(target) => {
+                    if let Some(pos) = pos.get(*target) {
+                        transform.set_translation_xyz(pos.x, pos.y, 1.0);
+                    }
+                }
+                CameraBehaviour::Pan(translation) => {
+                    let factor = f32::powf(2., 1. / zoom);
+                    transform.append_translation_xyz(
+                        translation.x * factor,
+                        translation.y * factor,
+                        0.,
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub struct UiRelativePositioning;
+
+impl<'a> System<'a> for UiRelativePositioning {
+    type SystemData = (
+        ReadExpect<'a, ScreenDimensions>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Hitbox>,
+        ReadStorage<'a, UiRelative>,
+        WriteStorage<'a, UiTransform>,
+    );
+
+    fn run(
+        &mut self,
+        (size, camera, transform, hitbox, ui_relative, mut ui_transform): Self::SystemData,
+    ) {
+        let size = size.diagonal();
+
+        for (camera, camera_transform) in (&camera, &transform).join() {
+            let projection = camera.projection();
+            for (ui_relative, ui_transform) in (&ui_relative, &mut ui_transform).join() {
+                let UiRelative(parent) = ui_relative;
+                if let Some(parent_transform) = transform.get(*parent) {
+                    let middle_world = (*parent_transform).clone();
+                    let middle_screen = projection.world_to_screen(
+                        math::Point::from(*parent_transform.translation()),
+                        size,
+                        camera_transform,
+                    );
+                    ui_transform.local_x = middle_screen.x;
+                    // TODO fix this inversion.
+                    ui_transform.local_y = size.y - middle_screen.y;
+
+                    if let Some(hitbox) = hitbox.get(*parent) {
+                        let topright_world = middle_world
+                            .clone()
+                            .prepend_translation(math::Vector3::new(
+                                hitbox.x / 2.,
+                                hitbox.y / 2.,
+                                0.,
+                            ))
+                            .clone();
+                        let topright_screen = projection.world_to_screen(
+                            math::Point::from(*topright_world.translation()),
+                            size,
+                            camera_transform,
+                        );
+
+                        let hwidth = topright_screen.x - middle_screen.x;
+                        let hheight = middle_screen.y - topright_screen.y;
+
+                        ui_transform.width = hwidth * 2.;
+                        ui_transform.height = hheight * 2.;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Number of notifications shown at once.
+const NOTIFICATIONS: usize = 5;
+
+/// Show the most recent events concerning the followed entity.
+#[derive(Default)]
+pub struct ShowNotifications {
+    reader: Option<ReaderId<SimEvent>>,
+    lines: VecDeque<String>,
+}
+
+impl<'a> System<'a> for ShowNotifications {
+    type SystemData = (
+        Read<'a, EventChannel<SimEvent>>,
+        ReadExpect<'a, CameraState>,
+        ReadExpect<'a, Galaxy>,
+        ReadExpect<'a, NotificationArea>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, UiText>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<SimEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, (events, camera_state, galaxy, area, name, mut text): Self::SystemData) {
+        let followed = match camera_state.behaviour {
+            CameraBehaviour::Follow(e) => Some(e),
+            _ => None,
+        };
+        let sector_name = |sector| galaxy.get(sector).map_or("?", |info| info.name.as_str());
+        let name_of = |e: Entity| {
+            name.get(e)
+                .map_or_else(|| format!("#{}", e.id()), |name| name.0.clone())
+        };
+
+        let reader = self.reader.as_mut().expect("reader is registered in setup");
+        let mut changed = false;
+        for event in events.read(reader) {
+            let line = match *event {
+                SimEvent::Arrived { ship, target } if Some(ship) == followed => {
+                    format!("{} arrived at {}", name_of(ship), name_of(target))
+                }
+                SimEvent::Jumped { ship, from, to } if Some(ship) == followed => format!(
+                    "{} jumped from {} to {}",
+                    name_of(ship),
+                    sector_name(from),
+                    sector_name(to)
+                ),
+                SimEvent::FabricationCompleted { station, recipe } if Some(station) == followed => {
+                    format!("{} fabricated {}", name_of(station), recipe)
+                }
+                _ => continue,
+            };
+
+            self.lines.push_back(line);
+            if self.lines.len() > NOTIFICATIONS {
+                self.lines.pop_front();
+            }
+            changed = true;
+        }
+
+        if changed {
+            if let Some(text) = text.get_mut(area.0) {
+                text.text = self.lines.iter().cloned().collect::<Vec<_>>().join("\n");
+            }
+        }
+    }
+}
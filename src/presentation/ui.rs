@@ -0,0 +1,126 @@
+use amethyst::{
+    assets::Handle,
+    ecs::{Builder, Component, Entity, NullStorage, VecStorage, World, WorldExt},
+    ui::{Anchor, FontAsset, LineMode, UiText, UiTransform},
+};
+
+use super::*;
+
+pub struct UiRelative(pub Entity);
+
+impl Component for UiRelative {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(Default)]
+pub struct UiSelectable;
+
+impl Component for UiSelectable {
+    type Storage = NullStorage<Self>;
+}
+
+pub fn create_ui_anchor(
+    world: &mut World,
+    target: &Entity,
+    name: &str,
+    selectable: bool,
+) -> Entity {
+    let mut res = world
+        .create_entity()
+        .with(UiRelative(*target))
+        .with(UiTransform::new(
+            compute_name(target, name),
+            Anchor::BottomLeft,
+            Anchor::Middle,
+            0.0,
+            0.0,
+            0.0,
+            0.,
+            0.,
+        ));
+
+    if selectable {
+        res = res.with(UiSelectable);
+    }
+
+    res.build()
+}
+
+pub fn create_ui_label(world: &mut World, anchor: &Entity, name: String) {
+    let font_handle = (*world.fetch::<Handle<FontAsset>>()).clone();
+
+    let mut ui_text = UiText::new(font_handle, name, [1., 1., 1., 1.], 10.);
+    ui_text.align = Anchor::BottomLeft;
+
+    world
+        .create_entity()
+        .with(Parent::new(*anchor))
+        .with(UiTransform::new(
+            "label".to_owned(),
+            Anchor::TopRight,
+            Anchor::TopLeft,
+            0.,
+            0.,
+            0.,
+            100.,
+            10.,
+        ))
+        .with(ui_text)
+        .build();
+}
+
+/// Text showing the notifications, see `ShowNotifications`.
+pub struct NotificationArea(pub Entity);
+
+pub fn create_notification_area(world: &mut World) {
+    let font_handle = (*world.fetch::<Handle<FontAsset>>()).clone();
+
+    let mut ui_text = UiText::new(font_handle, String::new(), [1., 1., 1., 1.], 12.);
+    ui_text.align = Anchor::TopLeft;
+    ui_text.line_mode = LineMode::Wrap;
+
+    let area = world
+        .create_entity()
+        .with(UiTransform::new(
+            "notifications".to_owned(),
+            Anchor::TopLeft,
+            Anchor::TopLeft,
+            10.,
+            -10.,
+            1.,
+            400.,
+            80.,
+        ))
+        .with(ui_text)
+        .build();
+
+    world.insert(NotificationArea(area));
+}
+
+/// Text showing the profile of the simulation, toggled by the player.
+pub struct ProfileOverlay(pub Entity);
+
+pub fn create_profile_overlay(world: &mut World) {
+    let font_handle = (*world.fetch::<Handle<FontAsset>>()).clone();
+
+    let mut ui_text = UiText::new(font_handle, String::new(), [1., 1., 0.6, 1.], 10.);
+    ui_text.align = Anchor::TopLeft;
+    ui_text.line_mode = LineMode::Wrap;
+
+    let overlay = world
+        .create_entity()
+        .with(UiTransform::new(
+            "profile".to_owned(),
+            Anchor::TopRight,
+            Anchor::TopRight,
+            -10.,
+            -10.,
+            1.,
+            320.,
+            400.,
+        ))
+        .with(ui_text)
+        .build();
+
+    world.insert(ProfileOverlay(overlay));
+}
@@ -0,0 +1,251 @@
+use amethyst::ecs::{join::Join, Component, DispatcherBuilder, System, World, WorldExt};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::components::*;
+
+/// Wall time spent in a single system.
+#[derive(Debug, Default)]
+pub struct SystemTiming {
+    last: AtomicU64,
+    max: AtomicU64,
+    total: AtomicU64,
+    runs: AtomicU64,
+}
+
+impl SystemTiming {
+    fn record(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos() as u64;
+        self.last.store(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+        self.total.fetch_add(nanos, Ordering::Relaxed);
+        self.runs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn last(&self) -> Duration {
+        Duration::from_nanos(self.last.load(Ordering::Relaxed))
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max.load(Ordering::Relaxed))
+    }
+
+    fn reset(&self) {
+        for counter in &[&self.last, &self.max, &self.total, &self.runs] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn average(&self) -> Duration {
+        let runs = self.runs.load(Ordering::Relaxed).max(1);
+        Duration::from_nanos(self.total.load(Ordering::Relaxed) / runs)
+    }
+}
+
+/// Timings of all profiled systems, and the rate at which ticks are simulated.
+///
+/// Systems keep their own handle to their timing, such that measuring does not make them contend for this resource.
+#[derive(Debug)]
+pub struct Profiler {
+    systems: Vec<(&'static str, Arc<SystemTiming>)>,
+    window_start: Instant,
+    window_ticks: u32,
+    ticks_per_second: f64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            systems: Vec::new(),
+            window_start: Instant::now(),
+            window_ticks: 0,
+            ticks_per_second: 0.,
+        }
+    }
+}
+
+impl Profiler {
+    fn register(&mut self, name: &'static str) -> Arc<SystemTiming> {
+        let timing = Arc::new(SystemTiming::default());
+        self.systems.push((name, timing.clone()));
+        timing
+    }
+
+    /// Count a simulated tick.
+    pub fn tick(&mut self) {
+        self.window_ticks += 1;
+
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.ticks_per_second = f64::from(self.window_ticks) / elapsed.as_secs_f64();
+            self.window_start = Instant::now();
+            self.window_ticks = 0;
+        }
+    }
+
+    /// Start measuring anew, keeping the systems registered.
+    pub fn reset(&mut self) {
+        for (_, timing) in &self.systems {
+            timing.reset();
+        }
+        self.window_start = Instant::now();
+        self.window_ticks = 0;
+        self.ticks_per_second = 0.;
+    }
+
+    /// Ticks simulated per second of wall time, over the last second.
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks_per_second
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = (&'static str, &SystemTiming)> {
+        self.systems.iter().map(|(name, timing)| (*name, &**timing))
+    }
+}
+
+/// System wrapper measuring the wall time of each run.
+pub struct Profiled<S> {
+    name: &'static str,
+    inner: S,
+    timing: Option<Arc<SystemTiming>>,
+}
+
+impl<'a, S: System<'a>> System<'a> for Profiled<S> {
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let start = Instant::now();
+        self.inner.run(data);
+
+        if let Some(timing) = &self.timing {
+            timing.record(start.elapsed());
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.inner.setup(world);
+        self.timing = Some(
+            world
+                .entry::<Profiler>()
+                .or_insert_with(Profiler::default)
+                .register(self.name),
+        );
+    }
+}
+
+pub trait WithProfiled<'a> {
+    /// Add a system, measuring how long it takes to run.
+    fn with_profiled<S>(self, system: S, name: &'static str, dep: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a;
+
+    /// Add a system, measuring how long it takes to run only if `profile` is set.
+    fn with_profiled_if<S>(
+        self,
+        profile: bool,
+        system: S,
+        name: &'static str,
+        dep: &[&str],
+    ) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a;
+}
+
+impl<'a, 'b> WithProfiled<'a> for DispatcherBuilder<'a, 'b> {
+    fn with_profiled<S>(self, system: S, name: &'static str, dep: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a,
+    {
+        let profiled = Profiled {
+            name,
+            inner: system,
+            timing: None,
+        };
+        self.with(profiled, name, dep)
+    }
+
+    fn with_profiled_if<S>(self, profile: bool, system: S, name: &'static str, dep: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a,
+    {
+        if profile {
+            self.with_profiled(system, name, dep)
+        } else {
+            self.with(system, name, dep)
+        }
+    }
+}
+
+fn count<T: Component>(world: &World) -> usize {
+    (&world.read_storage::<T>()).join().count()
+}
+
+/// Number of entities having each of the simulation components.
+pub fn component_counts(world: &World) -> Vec<(&'static str, usize)> {
+    vec![
+        ("Position", count::<Position>(world)),
+        ("Velocity", count::<Velocity>(world)),
+        ("ShipBehaviour", count::<ShipBehaviour>(world)),
+        ("Cargo", count::<Cargo>(world)),
+        ("FabricationModule", count::<FabricationModule>(world)),
+        ("Station", count::<Station>(world)),
+        ("Trader", count::<Trader>(world)),
+        ("Orbit", count::<Orbit>(world)),
+        ("Obstacle", count::<Obstacle>(world)),
+        ("CelestialBody", count::<CelestialBody>(world)),
+        ("AsteroidField", count::<AsteroidField>(world)),
+        ("JumpGate", count::<JumpGate>(world)),
+    ]
+}
+
+/// Human readable overview of the profile, with the slowest systems first.
+///
+/// Only the entities are counted if the systems are not profiled.
+pub fn report(world: &World) -> String {
+    let mut res = String::new();
+    match world.try_fetch::<Profiler>() {
+        Some(profiler) => {
+            let mut systems: Vec<(&'static str, &SystemTiming)> = profiler.systems().collect();
+            systems.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.average()));
+
+            let _ = writeln!(res, "ticks/s: {:.0}", profiler.ticks_per_second());
+            let _ = writeln!(
+                res,
+                "{:<20} {:>9} {:>9} {:>9}",
+                "system", "last us", "avg us", "max us"
+            );
+            for (name, timing) in systems {
+                let _ = writeln!(
+                    res,
+                    "{:<20} {:>9} {:>9} {:>9}",
+                    name,
+                    timing.last().as_micros(),
+                    timing.average().as_micros(),
+                    timing.max().as_micros()
+                );
+            }
+        }
+        None => {
+            let _ = writeln!(res, "systems are not profiled");
+        }
+    }
+
+    let _ = writeln!(res, "{:<20} {:>9}", "component", "entities");
+    for (name, count) in component_counts(world) {
+        let _ = writeln!(res, "{:<20} {:>9}", name, count);
+    }
+    res
+}
+
+/// Write the report to the given file.
+pub fn write_report(world: &World, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, report(world))
+}
@@ -0,0 +1,257 @@
+use amethyst::ecs::{join::Join, World, WorldExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::components::*;
+
+/// Parameters of a recipe, as given in `assets/recipes.ron`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeDef {
+    /// Name of one of the recipes in `RECIPES`.
+    pub name: String,
+    pub duration: u32,
+    #[serde(default)]
+    pub ingredients: Vec<(CargoType, i32)>,
+    pub products: Vec<(CargoType, i32)>,
+}
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, ron::de::Error),
+    Invalid { recipe: String, message: String },
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            RecipeError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            RecipeError::Invalid { recipe, message } => {
+                write!(f, "recipe {:?}: {}", recipe, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {}
+
+fn invalid(recipe: &str, message: &str) -> RecipeError {
+    RecipeError::Invalid {
+        recipe: recipe.to_owned(),
+        message: message.to_owned(),
+    }
+}
+
+fn units(amounts: &[(CargoType, i32)]) -> Vec<(CargoType, CargoUnits)> {
+    amounts
+        .iter()
+        .map(|&(t, amount)| (t, CargoUnits(amount)))
+        .collect()
+}
+
+/// Recipes leaked by `intern`, which are reused when the same parameters are loaded again.
+static LEAKED: Mutex<Vec<&'static FabricationRecipe>> = Mutex::new(Vec::new());
+
+/// The recipe with the given parameters, leaking it only if neither a built-in nor an earlier loaded recipe has them.
+fn intern(name: &'static str, def: &RecipeDef) -> &'static FabricationRecipe {
+    let duration = Time(def.duration);
+    let ingredients = units(&def.ingredients);
+    let products = units(&def.products);
+    let equal = |recipe: &FabricationRecipe| {
+        recipe.name == name
+            && recipe.duration == duration
+            && recipe.ingredients == &ingredients[..]
+            && recipe.products == &products[..]
+    };
+
+    if let Some(recipe) = RECIPES.iter().find(|recipe| equal(recipe)) {
+        return recipe;
+    }
+    let mut leaked = LEAKED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(&recipe) = leaked.iter().find(|recipe| equal(recipe)) {
+        return recipe;
+    }
+    let recipe = &*Box::leak(Box::new(FabricationRecipe {
+        name,
+        duration,
+        ingredients: Box::leak(ingredients.into_boxed_slice()),
+        products: Box::leak(products.into_boxed_slice()),
+    }));
+    leaked.push(recipe);
+    recipe
+}
+
+/// Current parameters of the recipes, which may be tweaked in `assets/recipes.ron` while the game is running.
+///
+/// The recipes themselves are those of `RECIPES`, as the generator relies on their production chains. Only their
+/// duration, ingredients and products can be changed.
+#[derive(Debug, Clone)]
+pub struct Recipes {
+    recipes: Vec<&'static FabricationRecipe>,
+}
+
+impl Default for Recipes {
+    fn default() -> Self {
+        Self {
+            recipes: RECIPES.iter().collect(),
+        }
+    }
+}
+
+impl Recipes {
+    /// Read and validate the recipes. Those which the file leaves out keep their built-in parameters.
+    pub fn load(path: &Path) -> Result<Self, RecipeError> {
+        let contents = fs::read_to_string(path).map_err(|e| RecipeError::Io(path.to_owned(), e))?;
+        let defs: Vec<RecipeDef> =
+            ron::de::from_str(&contents).map_err(|e| RecipeError::Parse(path.to_owned(), e))?;
+        Self::from_defs(&defs)
+    }
+
+    /// Override the built-in parameters by the given ones.
+    ///
+    /// Fabrication modules refer to their recipe for as long as the program runs, hence the new parameters are
+    /// leaked. Each distinct set of parameters is leaked only once, so reloading an unchanged file costs nothing.
+    pub fn from_defs(defs: &[RecipeDef]) -> Result<Self, RecipeError> {
+        let mut seen = HashSet::new();
+        for def in defs {
+            if recipe_by_name(&def.name).is_none() {
+                return Err(invalid(&def.name, "unknown recipe"));
+            }
+            if !seen.insert(def.name.as_str()) {
+                return Err(invalid(&def.name, "recipe is given more than once"));
+            }
+            if def.duration == 0 {
+                return Err(invalid(&def.name, "duration must be positive"));
+            }
+            if def.products.is_empty() {
+                return Err(invalid(&def.name, "recipe must have products"));
+            }
+            if def
+                .ingredients
+                .iter()
+                .chain(&def.products)
+                .any(|&(_, amount)| amount <= 0)
+            {
+                return Err(invalid(&def.name, "amounts must be positive"));
+            }
+        }
+
+        let recipes = RECIPES
+            .iter()
+            .map(
+                |recipe| match defs.iter().find(|def| def.name == recipe.name) {
+                    Some(def) => intern(recipe.name, def),
+                    None => recipe,
+                },
+            )
+            .collect();
+
+        Ok(Self { recipes })
+    }
+
+    /// Current parameters of all recipes, such as to recreate them by `from_defs`.
+    pub fn defs(&self) -> Vec<RecipeDef> {
+        let amounts = |amounts: &[(CargoType, CargoUnits)]| -> Vec<(CargoType, i32)> {
+            amounts.iter().map(|&(t, amount)| (t, *amount)).collect()
+        };
+
+        self.recipes
+            .iter()
+            .map(|recipe| RecipeDef {
+                name: recipe.name.to_owned(),
+                duration: *recipe.duration,
+                ingredients: amounts(recipe.ingredients),
+                products: amounts(recipe.products),
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'static FabricationRecipe> {
+        self.recipes
+            .iter()
+            .find(|recipe| recipe.name == name)
+            .copied()
+    }
+
+    /// Switch all fabrication modules over to the current parameters of their recipe.
+    ///
+    /// Their progress is kept: a batch under way finishes once it reaches the new duration, yielding the new products.
+    pub fn apply(&self, world: &World) {
+        for module in (&mut world.write_storage::<FabricationModule>()).join() {
+            if let Some(recipe) = self.get(module.recipe.name) {
+                module.recipe = recipe;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::Builder;
+
+    fn fuel(duration: u32) -> RecipeDef {
+        RecipeDef {
+            name: "fuel".to_owned(),
+            duration,
+            ingredients: vec![(CargoType::Water, 1)],
+            products: vec![(CargoType::Fuel, 3)],
+        }
+    }
+
+    #[test]
+    fn modules_keep_progress_with_new_parameters() {
+        let mut world = World::new();
+        world.register::<FabricationModule>();
+        let station = world
+            .create_entity()
+            .with(FabricationModule {
+                progress: Some(Time(40)),
+                recipe: recipe_by_name("fuel").unwrap(),
+            })
+            .build();
+
+        let recipes = Recipes::from_defs(&[fuel(60)]).unwrap();
+        recipes.apply(&world);
+
+        let storage = world.read_storage::<FabricationModule>();
+        let module = storage.get(station).unwrap();
+        assert_eq!(module.progress, Some(Time(40)));
+        assert_eq!(module.recipe.duration, Time(60));
+        assert_eq!(module.recipe.products, &[(CargoType::Fuel, CargoUnits(3))]);
+        assert_eq!(recipes.get("metal").unwrap().duration, Time(50));
+    }
+
+    #[test]
+    fn unchanged_recipes_are_reused() {
+        let built_in = Recipes::from_defs(&Recipes::default().defs()).unwrap();
+        for (recipe, original) in built_in.recipes.iter().zip(RECIPES.iter()) {
+            assert!(std::ptr::eq(*recipe, original));
+        }
+
+        let first = Recipes::from_defs(&[fuel(61)]).unwrap();
+        let second = Recipes::from_defs(&[fuel(61)]).unwrap();
+        assert!(std::ptr::eq(
+            first.get("fuel").unwrap(),
+            second.get("fuel").unwrap()
+        ));
+        let other = Recipes::from_defs(&[fuel(62)]).unwrap();
+        assert_eq!(other.get("fuel").unwrap().duration, Time(62));
+    }
+
+    #[test]
+    fn invalid_recipes_are_refused() {
+        assert!(Recipes::from_defs(&[fuel(0)]).is_err());
+        assert!(Recipes::from_defs(&[fuel(60), fuel(70)]).is_err());
+
+        let mut unknown = fuel(60);
+        unknown.name = "antimatter".to_owned();
+        assert!(Recipes::from_defs(&[unknown]).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use amethyst::ecs::{join::Join, prelude::ReadStorage, world::EntitiesRes, World, WorldExt};
+use log::{error, info};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::Command;
+use crate::components::*;
+use crate::recipes::{RecipeDef, Recipes};
+use crate::resources::CurrentTime;
+use crate::scenario::Scenario;
+use crate::ships::ShipClasses;
+
+/// Format version written to new recordings. Recordings of any other version are refused.
+pub const VERSION: u32 = 2;
+
+/// Everything needed to reproduce a session: the initial universe, the seed, the rules it ran by, and the commands
+/// issued by the player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub seed: u64,
+    pub scenario: Scenario,
+    /// Parameters of the recipes, which replays use instead of `assets/recipes.ron`.
+    pub recipes: Vec<RecipeDef>,
+    /// Ship classes, which replays use instead of `assets/ships.ron`.
+    pub ship_classes: ShipClasses,
+    /// Ticks simulated in total.
+    pub duration: Time,
+    /// Ticks between state hashes, if any are recorded.
+    pub hash_interval: Option<u32>,
+    pub commands: Vec<(Time, Command)>,
+    pub hashes: Vec<(Time, u64)>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Serialize(ron::ser::Error),
+    Deserialize(ron::de::Error),
+    Version(u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "could not access recording: {}", e),
+            ReplayError::Serialize(e) => write!(f, "could not write recording: {}", e),
+            ReplayError::Deserialize(e) => write!(f, "could not read recording: {}", e),
+            ReplayError::Version(version) => write!(
+                f,
+                "recording has version {}, while only version {} is supported",
+                version, VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<ron::ser::Error> for ReplayError {
+    fn from(e: ron::ser::Error) -> Self {
+        ReplayError::Serialize(e)
+    }
+}
+
+impl From<ron::de::Error> for ReplayError {
+    fn from(e: ron::de::Error) -> Self {
+        ReplayError::Deserialize(e)
+    }
+}
+
+impl Recording {
+    pub fn new(
+        seed: u64,
+        scenario: Scenario,
+        recipes: &Recipes,
+        ship_classes: &ShipClasses,
+        hash_interval: Option<u32>,
+    ) -> Self {
+        Self {
+            version: VERSION,
+            seed,
+            scenario,
+            recipes: recipes.defs(),
+            ship_classes: ship_classes.clone(),
+            duration: Time::default(),
+            hash_interval: hash_interval.filter(|&interval| interval > 0),
+            commands: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let serialized = ron::ser::to_string_pretty(self, PrettyConfig::default())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serialized)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let res: Recording = ron::de::from_str(&fs::read_to_string(path)?)?;
+        if res.version != VERSION {
+            return Err(ReplayError::Version(res.version));
+        }
+        Ok(res)
+    }
+}
+
+/// Progress through a recording being replayed.
+#[derive(Debug)]
+pub struct ReplayState {
+    commands: VecDeque<(Time, Command)>,
+    hashes: VecDeque<(Time, u64)>,
+    desync: Option<Time>,
+}
+
+impl ReplayState {
+    pub fn new(recording: &Recording) -> Self {
+        Self {
+            commands: recording.commands.iter().cloned().collect(),
+            hashes: recording.hashes.iter().cloned().collect(),
+            desync: None,
+        }
+    }
+
+    /// Take the commands which were issued at the given time.
+    pub fn due(&mut self, time: Time) -> Vec<Command> {
+        let mut res = Vec::new();
+        while let Some((_, command)) = self.commands.front().filter(|(t, _)| *t <= time) {
+            res.push(command.clone());
+            self.commands.pop_front();
+        }
+        res
+    }
+
+    pub fn expects_hash(&self, time: Time) -> bool {
+        self.hashes.front().map_or(false, |&(t, _)| t <= time)
+    }
+
+    /// Compare the state at the given time with the recording. Only the first divergence is reported.
+    pub fn verify(&mut self, time: Time, hash: u64) {
+        while let Some(&(t, expected)) = self.hashes.front().filter(|(t, _)| *t <= time) {
+            self.hashes.pop_front();
+
+            if t == time && expected != hash && self.desync.is_none() {
+                error!("Replay diverged from its recording at tick {}.", *time);
+                self.desync = Some(time);
+            }
+        }
+    }
+
+    /// First time at which the replay no longer matched the recording.
+    pub fn desync(&self) -> Option<Time> {
+        self.desync
+    }
+}
+
+/// 64 bit FNV-1a over values in little endian order.
+///
+/// Unlike the hasher of the standard library, its algorithm is fixed, such that hashes stored in a recording remain
+/// comparable across toolchains and platforms.
+struct StateHasher(u64);
+
+impl StateHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash of the state of all ships and stations, to compare replays with their recording.
+pub fn state_hash(
+    entities: &EntitiesRes,
+    pos: &ReadStorage<Position>,
+    vel: &ReadStorage<Velocity>,
+    cargo: &ReadStorage<Cargo>,
+    behaviour: &ReadStorage<ShipBehaviour>,
+) -> u64 {
+    let mut hasher = StateHasher::new();
+
+    for (e, pos, vel, cargo, behaviour) in
+        (entities, pos, vel.maybe(), cargo.maybe(), behaviour.maybe()).join()
+    {
+        hasher.write_u32(e.id());
+        hasher.write_f32(pos.x);
+        hasher.write_f32(pos.y);
+
+        if let Some(vel) = vel {
+            hasher.write_f32(vel.vector.x);
+            hasher.write_f32(vel.vector.y);
+        }
+
+        if let Some(cargo) = cargo {
+            for (_, amount) in cargo.iter() {
+                hasher.write(&amount.0.to_le_bytes());
+            }
+        }
+
+        match behaviour {
+            Some(ShipBehaviour::Idle) => hasher.write_u32(0),
+            Some(ShipBehaviour::FlyTo(target)) => {
+                hasher.write_u32(1);
+                hasher.write_u32(target.id());
+            }
+            Some(ShipBehaviour::FollowPath(target, path)) => {
+                hasher.write_u32(2);
+                hasher.write_u32(target.id());
+                hasher.write_u32(path.len() as u32);
+            }
+            Some(ShipBehaviour::Jump(gate, target)) => {
+                hasher.write_u32(3);
+                hasher.write_u32(gate.id());
+                hasher.write_u32(target.id());
+            }
+            None => (),
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Whether the commands of this run are recorded, replayed from an earlier recording, or neither.
+#[derive(Debug)]
+pub enum Session {
+    Live,
+    Record {
+        path: PathBuf,
+        hash_interval: Option<u32>,
+    },
+    Replay(Recording),
+}
+
+impl Session {
+    /// Start recording or replaying. To be called once the scenario has been created by the given rules.
+    pub fn begin(
+        &self,
+        world: &mut World,
+        seed: u64,
+        scenario: &Scenario,
+        recipes: &Recipes,
+        ship_classes: &ShipClasses,
+    ) {
+        match self {
+            Session::Live => (),
+            Session::Record {
+                hash_interval,
+                path,
+            } => {
+                info!("Recording to {:?}.", path);
+                world.insert(Recording::new(
+                    seed,
+                    scenario.clone(),
+                    recipes,
+                    ship_classes,
+                    *hash_interval,
+                ));
+            }
+            Session::Replay(recording) => {
+                world.insert(ReplayState::new(recording));
+            }
+        }
+    }
+
+    /// Write the recording, or report how the replay went.
+    pub fn finish(&self, world: &mut World) -> Result<(), ReplayError> {
+        match self {
+            Session::Live => (),
+            Session::Record { path, .. } => {
+                if let Some(mut recording) = world.remove::<Recording>() {
+                    recording.duration = world.read_resource::<CurrentTime>().0;
+                    recording.save(path)?;
+                    info!("Recording written to {:?}.", path);
+                }
+            }
+            Session::Replay(recording) => {
+                let time = world.read_resource::<CurrentTime>().0;
+                match world.read_resource::<ReplayState>().desync() {
+                    Some(desync) => error!("Replay diverged at tick {}.", *desync),
+                    None if time < recording.duration => {
+                        info!(
+                            "Replay stopped at tick {} of {}.",
+                            *time, *recording.duration
+                        )
+                    }
+                    None => info!("Replay matched its recording."),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::Builder;
+
+    #[test]
+    fn hasher_is_fnv1a() {
+        let mut hasher = StateHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn state_hash_is_stable() {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.register::<Cargo>();
+        world.register::<ShipBehaviour>();
+
+        let station = world
+            .create_entity()
+            .with(Position::new(Point2::new(1., 2.)))
+            .with(Velocity::new(Translation2::new(0.5, 0.)))
+            .with(ShipBehaviour::Idle)
+            .build();
+        world
+            .create_entity()
+            .with(Position::new(Point2::new(3., 4.)))
+            .with(ShipBehaviour::FlyTo(station))
+            .build();
+
+        // Pinned, as recordings made by any build must verify with any other.
+        let entities = world.entities();
+        let hash = state_hash(
+            &entities,
+            &world.read_storage(),
+            &world.read_storage(),
+            &world.read_storage(),
+            &world.read_storage(),
+        );
+        assert_eq!(hash, 0xabd2_e9bf_cf6e_f945);
+    }
+}
@@ -1,25 +1,124 @@
-use crate::components::{Time, Translation2};
-use amethyst::ecs::Entity;
-use std::collections::HashSet;
-
-#[derive(Debug)]
-pub enum CameraBehaviour {
-    Static,
-    Pan(Translation2),
-    Follow(Entity),
+use crate::components::Time;
+use rand::SeedableRng;
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Default)]
+pub struct CurrentTime(pub Time);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimulationSpeed {
+    Paused,
+    /// Run the given number of ticks per tick of real time.
+    Times(u32),
+    /// Run as many ticks as fit in a frame.
+    Max,
 }
 
-#[derive(Debug)]
-pub struct CameraState {
-    pub zoom: f32,
-    pub behaviour: CameraBehaviour,
+impl Default for SimulationSpeed {
+    fn default() -> Self {
+        SimulationSpeed::Times(1)
+    }
 }
 
-#[derive(Debug, Default)]
-pub struct Selection(pub Option<Entity>);
+impl SimulationSpeed {
+    /// Highest number of ticks per tick of real time, beyond which `Max` is the way to go faster.
+    pub const MAX_FACTOR: u32 = 10;
 
-#[derive(Debug, Default)]
-pub struct HoverSelectable(pub HashSet<Entity>);
+    const LADDER: [SimulationSpeed; 6] = [
+        SimulationSpeed::Paused,
+        SimulationSpeed::Times(1),
+        SimulationSpeed::Times(2),
+        SimulationSpeed::Times(5),
+        SimulationSpeed::Times(Self::MAX_FACTOR),
+        SimulationSpeed::Max,
+    ];
+
+    fn step(self, offset: isize) -> Self {
+        let current = Self::LADDER.iter().position(|&s| s == self).unwrap_or(1) as isize;
+        let new = (current + offset)
+            .max(0)
+            .min(Self::LADDER.len() as isize - 1);
+        Self::LADDER[new as usize]
+    }
+
+    pub fn faster(self) -> Self {
+        self.step(1)
+    }
+
+    pub fn slower(self) -> Self {
+        self.step(-1)
+    }
+
+    /// Change to the given speed. When this pauses the simulation, the speed paused from is remembered to resume at.
+    pub fn change(&mut self, new: SimulationSpeed, resume: &mut ResumeSpeed) {
+        if new == SimulationSpeed::Paused && *self != SimulationSpeed::Paused {
+            resume.0 = *self;
+        }
+        *self = new;
+    }
+}
+
+/// Speed the simulation was paused from, to resume at, whether it was paused by the player or through the API.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResumeSpeed(pub SimulationSpeed);
 
+/// Parses `paused`, `max`, or the number of ticks per tick of real time, up to `MAX_FACTOR`.
+impl FromStr for SimulationSpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "paused" => Ok(SimulationSpeed::Paused),
+            "max" => Ok(SimulationSpeed::Max),
+            _ => match s.parse() {
+                Ok(0) => Ok(SimulationSpeed::Paused),
+                Ok(factor) if factor <= Self::MAX_FACTOR => Ok(SimulationSpeed::Times(factor)),
+                _ => Err(format!(
+                    "expected paused, max or a number of ticks up to {}, got {}",
+                    Self::MAX_FACTOR,
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Ticks requested to be simulated while paused, to step through the simulation.
 #[derive(Debug, Default)]
-pub struct CurrentTime(pub Time);
+pub struct PendingSteps(pub u32);
+
+/// Source of all randomness in the simulation, such that a run can be reproduced from its seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimRng(pub Pcg64Mcg);
+
+impl SimRng {
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self(Pcg64Mcg::seed_from_u64(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_factor_is_capped() {
+        assert_eq!("5".parse(), Ok(SimulationSpeed::Times(5)));
+        assert_eq!("0".parse(), Ok(SimulationSpeed::Paused));
+        assert!("11".parse::<SimulationSpeed>().is_err());
+        assert!("4294967295".parse::<SimulationSpeed>().is_err());
+    }
+
+    #[test]
+    fn pausing_remembers_speed() {
+        let mut speed = SimulationSpeed::Times(5);
+        let mut resume = ResumeSpeed::default();
+
+        speed.change(SimulationSpeed::Paused, &mut resume);
+        speed.change(SimulationSpeed::Paused, &mut resume);
+        assert_eq!(speed, SimulationSpeed::Paused);
+        assert_eq!(resume.0, SimulationSpeed::Times(5));
+    }
+}
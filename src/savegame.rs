@@ -0,0 +1,588 @@
+use amethyst::ecs::{join::Join, Component, Entity, World, WorldExt};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::components::*;
+use crate::factions::{FactionInfo, Factions};
+use crate::galaxy::{Galaxy, SectorInfo};
+use crate::navigation::Navigation;
+use crate::recipes::Recipes;
+use crate::resources::*;
+
+/// Format version written to new saves. Saves of any other version are refused.
+pub const VERSION: u32 = 3;
+
+/// Reference to another entity in the same save, as its index in `SaveGame::entities`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityRef(pub u32);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedBehaviour {
+    Idle,
+    FlyTo(EntityRef),
+    FollowPath(EntityRef, VecDeque<Point2>),
+    Jump(EntityRef, EntityRef),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedOrbit {
+    pub center: EntityRef,
+    pub radius: f32,
+    pub period: Time,
+    pub phase: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFabrication {
+    /// Name of the recipe, see `recipe_by_name`.
+    pub recipe: String,
+    pub progress: Option<Time>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedValue {
+    Entity(EntityRef),
+    Time(Time),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedBrain {
+    pub tree: String,
+    pub blackboard: Vec<(String, SavedValue)>,
+}
+
+/// All simulation components of a single entity.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedEntity {
+    pub station: bool,
+    pub trader: bool,
+    pub ship: Option<ShipStats>,
+    pub name: Option<Name>,
+    pub sector: Option<Sector>,
+    pub owner: Option<Faction>,
+    pub position: Option<Position>,
+    pub velocity: Option<Velocity>,
+    pub hitbox: Option<Hitbox>,
+    pub obstacle: Option<Obstacle>,
+    pub angle: Option<Angle>,
+    pub angular_momentum: Option<AngularMomentum>,
+    pub cargo: Option<Cargo>,
+    pub fabrication: Option<SavedFabrication>,
+    pub behaviour: Option<SavedBehaviour>,
+    pub brain: Option<SavedBrain>,
+    pub orbit: Option<SavedOrbit>,
+    pub celestial_body: Option<CelestialBody>,
+    pub asteroid_field: Option<AsteroidField>,
+    pub script: Option<Scripted>,
+    /// Destination of a jump gate.
+    pub jump_gate: Option<EntityRef>,
+}
+
+/// What the player was looking at, restored by the presentation layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedView {
+    pub zoom: f32,
+    pub sector: Sector,
+    pub follow: Option<EntityRef>,
+}
+
+/// Complete state of the simulation, from which it continues exactly where it was left.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub time: Time,
+    pub rng: SimRng,
+    pub sectors: Vec<SectorInfo>,
+    pub factions: Vec<FactionInfo>,
+    pub entities: Vec<SavedEntity>,
+    pub view: Option<SavedView>,
+    #[serde(skip)]
+    refs: HashMap<Entity, EntityRef>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(ron::ser::Error),
+    Deserialize(ron::de::Error),
+    Version(u32),
+    UnknownRecipe(String),
+    UnknownEntity(EntityRef),
+    InvalidOrbit(EntityRef, &'static str),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "could not access save: {}", e),
+            SaveError::Serialize(e) => write!(f, "could not write save: {}", e),
+            SaveError::Deserialize(e) => write!(f, "could not read save: {}", e),
+            SaveError::Version(version) => write!(
+                f,
+                "save has version {}, while only version {} is supported",
+                version, VERSION
+            ),
+            SaveError::UnknownRecipe(name) => write!(f, "save uses unknown recipe {}", name),
+            SaveError::UnknownEntity(r) => write!(f, "save refers to unknown entity {}", r.0),
+            SaveError::InvalidOrbit(r, message) => {
+                write!(f, "orbit of entity {} in save: {}", r.0, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<ron::ser::Error> for SaveError {
+    fn from(e: ron::ser::Error) -> Self {
+        SaveError::Serialize(e)
+    }
+}
+
+impl From<ron::de::Error> for SaveError {
+    fn from(e: ron::de::Error) -> Self {
+        SaveError::Deserialize(e)
+    }
+}
+
+impl SavedEntity {
+    /// All other entities this entity refers to.
+    fn references(&self) -> Vec<EntityRef> {
+        let mut res = Vec::new();
+        match &self.behaviour {
+            Some(SavedBehaviour::FlyTo(target)) | Some(SavedBehaviour::FollowPath(target, _)) => {
+                res.push(*target)
+            }
+            Some(SavedBehaviour::Jump(gate, target)) => res.extend(&[*gate, *target]),
+            Some(SavedBehaviour::Idle) | None => (),
+        }
+        if let Some(brain) = &self.brain {
+            res.extend(
+                brain
+                    .blackboard
+                    .iter()
+                    .filter_map(|(_, value)| match value {
+                        SavedValue::Entity(r) => Some(*r),
+                        SavedValue::Time(_) => None,
+                    }),
+            );
+        }
+        res.extend(self.orbit.as_ref().map(|orbit| orbit.center));
+        res.extend(self.jump_gate);
+        res
+    }
+}
+
+impl SaveGame {
+    /// Capture all simulated entities, being those which reside in a sector, and the simulation resources.
+    pub fn capture(world: &World) -> Self {
+        let entities = world.entities();
+        let station = world.read_storage::<Station>();
+        let trader = world.read_storage::<Trader>();
+        let ship = world.read_storage::<ShipStats>();
+        let name = world.read_storage::<Name>();
+        let sector = world.read_storage::<Sector>();
+        let owner = world.read_storage::<Faction>();
+        let position = world.read_storage::<Position>();
+        let velocity = world.read_storage::<Velocity>();
+        let hitbox = world.read_storage::<Hitbox>();
+        let obstacle = world.read_storage::<Obstacle>();
+        let angle = world.read_storage::<Angle>();
+        let angular_momentum = world.read_storage::<AngularMomentum>();
+        let cargo = world.read_storage::<Cargo>();
+        let fabrication = world.read_storage::<FabricationModule>();
+        let behaviour = world.read_storage::<ShipBehaviour>();
+        let brain = world.read_storage::<Brain>();
+        let orbit = world.read_storage::<Orbit>();
+        let celestial_body = world.read_storage::<CelestialBody>();
+        let asteroid_field = world.read_storage::<AsteroidField>();
+        let script = world.read_storage::<Scripted>();
+        let jump_gate = world.read_storage::<JumpGate>();
+
+        let saved: Vec<Entity> = (&entities, &sector).join().map(|(e, _)| e).collect();
+        let refs: HashMap<Entity, EntityRef> = saved
+            .iter()
+            .enumerate()
+            .map(|(i, &e)| (e, EntityRef(i as u32)))
+            .collect();
+        let to_ref = |e: Entity| refs.get(&e).copied();
+
+        let entities = saved
+            .iter()
+            .map(|&e| SavedEntity {
+                station: station.contains(e),
+                trader: trader.contains(e),
+                ship: ship.get(e).cloned(),
+                name: name.get(e).cloned(),
+                sector: sector.get(e).copied(),
+                owner: owner.get(e).copied(),
+                position: position.get(e).copied(),
+                velocity: velocity.get(e).copied(),
+                hitbox: hitbox.get(e).copied(),
+                obstacle: obstacle.get(e).copied(),
+                angle: angle.get(e).copied(),
+                angular_momentum: angular_momentum.get(e).copied(),
+                cargo: cargo.get(e).cloned(),
+                fabrication: fabrication.get(e).map(|module| SavedFabrication {
+                    recipe: module.recipe.name.to_owned(),
+                    progress: module.progress,
+                }),
+                behaviour: behaviour.get(e).map(|behaviour| match behaviour {
+                    ShipBehaviour::Idle => SavedBehaviour::Idle,
+                    ShipBehaviour::FlyTo(target) => {
+                        to_ref(*target).map_or(SavedBehaviour::Idle, SavedBehaviour::FlyTo)
+                    }
+                    ShipBehaviour::FollowPath(target, path) => to_ref(*target)
+                        .map_or(SavedBehaviour::Idle, |target| {
+                            SavedBehaviour::FollowPath(target, path.clone())
+                        }),
+                    ShipBehaviour::Jump(gate, target) => match (to_ref(*gate), to_ref(*target)) {
+                        (Some(gate), Some(target)) => SavedBehaviour::Jump(gate, target),
+                        _ => SavedBehaviour::Idle,
+                    },
+                }),
+                // Entries referring to entities which are not saved are dropped, as the tree finds out about those
+                // itself.
+                brain: brain.get(e).map(|brain| SavedBrain {
+                    tree: brain.tree.clone(),
+                    blackboard: brain
+                        .blackboard
+                        .iter()
+                        .filter_map(|(key, value)| {
+                            let value = match value {
+                                Value::Entity(target) => SavedValue::Entity(to_ref(target)?),
+                                Value::Time(t) => SavedValue::Time(t),
+                            };
+                            Some((key.to_owned(), value))
+                        })
+                        .collect(),
+                }),
+                orbit: orbit.get(e).and_then(|orbit| {
+                    Some(SavedOrbit {
+                        center: to_ref(orbit.center)?,
+                        radius: orbit.radius,
+                        period: orbit.period,
+                        phase: orbit.phase,
+                    })
+                }),
+                celestial_body: celestial_body.get(e).copied(),
+                asteroid_field: asteroid_field.get(e).copied(),
+                script: script.get(e).cloned(),
+                jump_gate: jump_gate.get(e).and_then(|gate| to_ref(gate.destination)),
+            })
+            .collect();
+
+        SaveGame {
+            version: VERSION,
+            time: world.read_resource::<CurrentTime>().0,
+            rng: (*world.read_resource::<SimRng>()).clone(),
+            sectors: world
+                .read_resource::<Galaxy>()
+                .sectors()
+                .map(|(_, info)| info.clone())
+                .collect(),
+            factions: world
+                .read_resource::<Factions>()
+                .factions()
+                .map(|(_, info)| info.clone())
+                .collect(),
+            entities,
+            view: None,
+            refs,
+        }
+    }
+
+    /// Reference to a captured entity, for storing presentation state alongside the simulation.
+    pub fn entity_ref(&self, entity: Entity) -> Option<EntityRef> {
+        self.refs.get(&entity).copied()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+        let serialized = ron::ser::to_string_pretty(self, PrettyConfig::default())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serialized)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SaveError> {
+        let res: SaveGame = ron::de::from_str(&fs::read_to_string(path)?)?;
+        if res.version != VERSION {
+            return Err(SaveError::Version(res.version));
+        }
+        Ok(res)
+    }
+
+    /// Check that every orbit has a period and ends at a body which does not orbit, as positions could not be
+    /// computed otherwise.
+    ///
+    /// All references must have been checked to exist.
+    fn validate_orbits(&self) -> Result<(), SaveError> {
+        let center = |r: EntityRef| {
+            self.entities[r.0 as usize]
+                .orbit
+                .as_ref()
+                .map(|orbit| orbit.center)
+        };
+
+        for (i, saved) in self.entities.iter().enumerate() {
+            let r = EntityRef(i as u32);
+            if let Some(SavedOrbit {
+                period: Time(0), ..
+            }) = saved.orbit
+            {
+                return Err(SaveError::InvalidOrbit(r, "period must be positive"));
+            }
+
+            // A chain of orbits longer than the number of entities must come back around.
+            let mut current = center(r);
+            for _ in 0..self.entities.len() {
+                current = match current {
+                    Some(c) => center(c),
+                    None => break,
+                };
+            }
+            if current.is_some() {
+                return Err(SaveError::InvalidOrbit(r, "orbits form a loop"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check everything which could keep the save from being applied to the world, without changing it.
+    pub fn validate(&self, world: &World) -> Result<(), SaveError> {
+        if let Some(module) = self
+            .entities
+            .iter()
+            .filter_map(|saved| saved.fabrication.as_ref())
+            .find(|module| recipe(world, &module.recipe).is_none())
+        {
+            return Err(SaveError::UnknownRecipe(module.recipe.clone()));
+        }
+
+        let count = self.entities.len() as u32;
+        let view_references = self.view.as_ref().and_then(|view| view.follow);
+        if let Some(r) = self
+            .entities
+            .iter()
+            .flat_map(SavedEntity::references)
+            .chain(view_references)
+            .find(|r| r.0 >= count)
+        {
+            return Err(SaveError::UnknownEntity(r));
+        }
+        self.validate_orbits()
+    }
+
+    /// Validate the save and recreate it in the world, which is left untouched if the save is invalid.
+    ///
+    /// The world is expected to hold no simulated entities. Yields the created entities, indexed by `EntityRef`.
+    pub fn restore(self, world: &mut World) -> Result<Vec<Entity>, SaveError> {
+        self.validate(world)?;
+        Ok(self.apply(world))
+    }
+
+    /// Recreate the saved entities and resources in the world, as `restore` does for a save which has been
+    /// validated already.
+    ///
+    /// Panics if the save is invalid.
+    pub fn apply(self, world: &mut World) -> Vec<Entity> {
+        let mut galaxy = Galaxy::default();
+        for info in &self.sectors {
+            galaxy.add_sector(&info.name, info.position);
+        }
+        world.insert(galaxy);
+        let mut factions = Factions::default();
+        for info in &self.factions {
+            factions.add_faction(&info.name, info.credits);
+        }
+        world.insert(factions);
+        world.insert(Navigation::default());
+        world.insert(CurrentTime(self.time));
+        world.insert(self.rng);
+
+        let created: Vec<Entity> = self
+            .entities
+            .iter()
+            .map(|_| world.create_entity().build())
+            .collect();
+        let resolve = |r: EntityRef| created[r.0 as usize];
+
+        for (saved, &e) in self.entities.into_iter().zip(&created) {
+            let fabrication = saved.fabrication.map(|module| FabricationModule {
+                progress: module.progress,
+                recipe: recipe(world, &module.recipe).expect("save was validated"),
+            });
+
+            if saved.station {
+                insert(world, e, Some(Station));
+            }
+            if saved.trader {
+                insert(world, e, Some(Trader));
+            }
+            insert(world, e, saved.ship);
+            insert(world, e, saved.name);
+            insert(world, e, saved.sector);
+            insert(world, e, saved.owner);
+            insert(world, e, saved.position);
+            insert(world, e, saved.velocity);
+            insert(world, e, saved.hitbox);
+            insert(world, e, saved.obstacle);
+            insert(world, e, saved.angle);
+            insert(world, e, saved.angular_momentum);
+            insert(world, e, saved.cargo);
+            insert(world, e, fabrication);
+            insert(
+                world,
+                e,
+                saved.behaviour.map(|behaviour| match behaviour {
+                    SavedBehaviour::Idle => ShipBehaviour::Idle,
+                    SavedBehaviour::FlyTo(target) => ShipBehaviour::FlyTo(resolve(target)),
+                    SavedBehaviour::FollowPath(target, path) => {
+                        ShipBehaviour::FollowPath(resolve(target), path)
+                    }
+                    SavedBehaviour::Jump(gate, target) => {
+                        ShipBehaviour::Jump(resolve(gate), resolve(target))
+                    }
+                }),
+            );
+            insert(
+                world,
+                e,
+                saved.brain.map(|saved| {
+                    let mut brain = Brain::new(&saved.tree);
+                    for (key, value) in saved.blackboard {
+                        let value = match value {
+                            SavedValue::Entity(r) => Value::Entity(resolve(r)),
+                            SavedValue::Time(t) => Value::Time(t),
+                        };
+                        brain.blackboard.set(&key, value);
+                    }
+                    brain
+                }),
+            );
+            insert(
+                world,
+                e,
+                saved.orbit.map(|orbit| Orbit {
+                    center: resolve(orbit.center),
+                    radius: orbit.radius,
+                    period: orbit.period,
+                    phase: orbit.phase,
+                }),
+            );
+            insert(world, e, saved.celestial_body);
+            insert(world, e, saved.asteroid_field);
+            insert(world, e, saved.script);
+            insert(
+                world,
+                e,
+                saved.jump_gate.map(|destination| JumpGate {
+                    destination: resolve(destination),
+                }),
+            );
+        }
+
+        created
+    }
+}
+
+/// Recipe of the given name, with its current parameters if those have been loaded.
+fn recipe(world: &World, name: &str) -> Option<&'static FabricationRecipe> {
+    match world.try_fetch::<Recipes>() {
+        Some(recipes) => recipes.get(name),
+        None => recipe_by_name(name),
+    }
+}
+
+fn insert<T: Component>(world: &World, entity: Entity, component: Option<T>) {
+    if let Some(component) = component {
+        world
+            .write_storage::<T>()
+            .insert(entity, component)
+            .expect("entity was just created");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orbiting(center: u32, period: u32) -> SavedEntity {
+        SavedEntity {
+            sector: Some(Sector(0)),
+            orbit: Some(SavedOrbit {
+                center: EntityRef(center),
+                radius: 100.,
+                period: Time(period),
+                phase: 0.,
+            }),
+            ..SavedEntity::default()
+        }
+    }
+
+    fn save(entities: Vec<SavedEntity>) -> SaveGame {
+        SaveGame {
+            version: VERSION,
+            time: Time(0),
+            rng: SimRng::seed_from_u64(0),
+            sectors: Vec::new(),
+            factions: Vec::new(),
+            entities,
+            view: None,
+            refs: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn invalid_orbits_are_refused() {
+        let star = SavedEntity {
+            sector: Some(Sector(0)),
+            ..SavedEntity::default()
+        };
+        assert!(save(vec![star.clone(), orbiting(0, 100)])
+            .validate_orbits()
+            .is_ok());
+        assert!(matches!(
+            save(vec![star, orbiting(0, 0)]).validate_orbits(),
+            Err(SaveError::InvalidOrbit(EntityRef(1), _))
+        ));
+        assert!(matches!(
+            save(vec![orbiting(1, 100), orbiting(2, 100), orbiting(0, 100)]).validate_orbits(),
+            Err(SaveError::InvalidOrbit(..))
+        ));
+    }
+
+    #[test]
+    fn invalid_saves_leave_world_untouched() {
+        let mut world = World::new();
+        let factory = SavedEntity {
+            sector: Some(Sector(0)),
+            fabrication: Some(SavedFabrication {
+                recipe: "antimatter".to_owned(),
+                progress: None,
+            }),
+            ..SavedEntity::default()
+        };
+
+        assert!(matches!(
+            save(vec![factory]).restore(&mut world),
+            Err(SaveError::UnknownRecipe(_))
+        ));
+        assert!(!world.has_value::<Galaxy>());
+        assert!(!world.has_value::<CurrentTime>());
+    }
+}
@@ -0,0 +1,705 @@
+use amethyst::ecs::{Entity, World, WorldExt};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::*;
+use crate::factions::Factions;
+use crate::galaxy::Galaxy;
+use crate::names::NameGenerator;
+use crate::scripting::Scripts;
+use crate::ships::ShipClasses;
+
+/// Initial universe, as described in `assets/scenarios/*.ron`.
+///
+/// Stations and celestial bodies may be given a name, by which orbits and ship behaviours refer to them. Names must be
+/// unique over all sectors, and an orbit can only refer to a body or station defined before it in the same sector.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub factions: Vec<FactionDef>,
+    pub sectors: Vec<SectorDef>,
+    #[serde(default)]
+    pub jump_gates: Vec<JumpGateDef>,
+    /// Script steering the ships of a class, unless they have a script of their own.
+    #[serde(default)]
+    pub class_scripts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FactionDef {
+    pub name: String,
+    /// Starting credits.
+    #[serde(default)]
+    pub credits: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SectorDef {
+    pub name: String,
+    /// Location on the galaxy map.
+    pub position: (f32, f32),
+    #[serde(default)]
+    pub bodies: Vec<BodyDef>,
+    #[serde(default)]
+    pub stations: Vec<StationDef>,
+    #[serde(default)]
+    pub asteroid_fields: Vec<AsteroidFieldDef>,
+    #[serde(default)]
+    pub ships: Vec<ShipDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LocationDef {
+    At(f32, f32),
+    Orbit {
+        center: String,
+        radius: f32,
+        period: u32,
+        #[serde(default)]
+        phase: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyDef {
+    pub name: String,
+    pub kind: CelestialBody,
+    pub radius: f32,
+    pub location: LocationDef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StationDef {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub location: LocationDef,
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Name of the recipe the station fabricates, if any.
+    #[serde(default)]
+    pub module: Option<String>,
+    #[serde(default)]
+    pub cargo: Vec<(CargoType, i32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsteroidFieldDef {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub ore: CargoType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BehaviourDef {
+    Idle,
+    /// Fly to the named station or body.
+    FlyTo(String),
+}
+
+impl Default for BehaviourDef {
+    fn default() -> Self {
+        BehaviourDef::Idle
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipDef {
+    pub class: String,
+    pub owner: String,
+    pub position: (f32, f32),
+    #[serde(default)]
+    pub behaviour: BehaviourDef,
+    #[serde(default)]
+    pub cargo: Vec<(CargoType, i32)>,
+    /// Script in `assets/scripts` deciding where the ship goes, instead of the built-in behaviour.
+    #[serde(default)]
+    pub script: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateEndDef {
+    pub sector: String,
+    pub position: (f32, f32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JumpGateDef {
+    pub a: GateEndDef,
+    pub b: GateEndDef,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, ron::de::Error),
+    Write(PathBuf, ron::ser::Error),
+    /// The scenario parsed, but does not make sense. Context tells where in the scenario the problem is.
+    Invalid {
+        context: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ScenarioError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ScenarioError::Write(path, e) => write!(f, "{}: {}", path.display(), e),
+            ScenarioError::Invalid { context, message } => write!(f, "{}: {}", context, message),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+fn invalid(context: &str, message: String) -> ScenarioError {
+    ScenarioError::Invalid {
+        context: context.to_owned(),
+        message,
+    }
+}
+
+fn point((x, y): (f32, f32)) -> Point2 {
+    Point2::new(x, y)
+}
+
+impl Scenario {
+    /// Read and validate a scenario, whose ships must be of the given classes and run the given scripts.
+    pub fn load(
+        path: &Path,
+        classes: &ShipClasses,
+        scripts: &Scripts,
+    ) -> Result<Self, ScenarioError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ScenarioError::Io(path.to_owned(), e))?;
+        let res: Scenario =
+            ron::de::from_str(&contents).map_err(|e| ScenarioError::Parse(path.to_owned(), e))?;
+        res.validate(classes, scripts)?;
+        Ok(res)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ScenarioError> {
+        let serialized = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| ScenarioError::Write(path.to_owned(), e))?;
+        fs::write(path, serialized).map_err(|e| ScenarioError::Io(path.to_owned(), e))
+    }
+
+    /// Check all references, names and quantities, such that the scenario can be created without failure.
+    pub fn validate(&self, classes: &ShipClasses, scripts: &Scripts) -> Result<(), ScenarioError> {
+        let mut factions = HashSet::new();
+        for faction in &self.factions {
+            if !factions.insert(faction.name.as_str()) {
+                return Err(invalid(
+                    &format!("faction {:?}", faction.name),
+                    "name is not unique".to_owned(),
+                ));
+            }
+        }
+
+        let mut sectors = HashSet::new();
+        for sector in &self.sectors {
+            if !sectors.insert(sector.name.as_str()) {
+                return Err(invalid(
+                    &format!("sector {:?}", sector.name),
+                    "name is not unique".to_owned(),
+                ));
+            }
+        }
+
+        for (class, script) in &self.class_scripts {
+            if classes.get(class).is_none() {
+                return Err(invalid(
+                    "class scripts",
+                    format!("unknown ship class {:?}", class),
+                ));
+            }
+            if scripts.get(script).is_none() {
+                return Err(invalid(
+                    "class scripts",
+                    format!("unknown script {:?}", script),
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+
+        for sector in &self.sectors {
+            // Orbits cannot cross sectors, so their centers are looked up among the names of this sector only.
+            let mut centers = HashSet::new();
+
+            for (i, body) in sector.bodies.iter().enumerate() {
+                let context = format!("sector {:?}, body {}", sector.name, i);
+                validate_location(&body.location, &centers, &context)?;
+                if body.radius <= 0. {
+                    return Err(invalid(&context, "radius must be positive".to_owned()));
+                }
+                declare(&mut names, &body.name, &context)?;
+                centers.insert(body.name.as_str());
+            }
+
+            for (i, station) in sector.stations.iter().enumerate() {
+                let context = format!("sector {:?}, station {}", sector.name, i);
+                validate_location(&station.location, &centers, &context)?;
+                if let Some(owner) = &station.owner {
+                    if !factions.contains(owner.as_str()) {
+                        return Err(invalid(&context, format!("unknown owner {:?}", owner)));
+                    }
+                }
+                if let Some(module) = &station.module {
+                    if recipe_by_name(module).is_none() {
+                        return Err(invalid(&context, format!("unknown recipe {:?}", module)));
+                    }
+                }
+                validate_cargo(&station.cargo, STATION_CAPACITY, &context)?;
+                if let Some(name) = &station.name {
+                    declare(&mut names, name, &context)?;
+                    centers.insert(name.as_str());
+                }
+            }
+
+            for (i, field) in sector.asteroid_fields.iter().enumerate() {
+                let context = format!("sector {:?}, asteroid field {}", sector.name, i);
+                validate_position(field.position, &context)?;
+                if field.radius <= 0. {
+                    return Err(invalid(&context, "radius must be positive".to_owned()));
+                }
+            }
+        }
+
+        for sector in &self.sectors {
+            for (i, ship) in sector.ships.iter().enumerate() {
+                let context = format!("sector {:?}, ship {}", sector.name, i);
+                validate_position(ship.position, &context)?;
+                let class = classes.get(&ship.class).ok_or_else(|| {
+                    invalid(&context, format!("unknown ship class {:?}", ship.class))
+                })?;
+                if !factions.contains(ship.owner.as_str()) {
+                    return Err(invalid(&context, format!("unknown owner {:?}", ship.owner)));
+                }
+                if let BehaviourDef::FlyTo(target) = &ship.behaviour {
+                    if !names.contains(target) {
+                        return Err(invalid(&context, format!("unknown target {:?}", target)));
+                    }
+                }
+                validate_cargo(&ship.cargo, CargoUnits(class.capacity), &context)?;
+                if let Some(script) = &ship.script {
+                    if scripts.get(script).is_none() {
+                        return Err(invalid(&context, format!("unknown script {:?}", script)));
+                    }
+                }
+            }
+        }
+
+        for (i, gate) in self.jump_gates.iter().enumerate() {
+            let context = format!("jump gate {}", i);
+            for end in &[&gate.a, &gate.b] {
+                if !sectors.contains(end.sector.as_str()) {
+                    return Err(invalid(
+                        &context,
+                        format!("unknown sector {:?}", end.sector),
+                    ));
+                }
+                validate_position(end.position, &context)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Populate the world with the scenario, yielding the first ship as the one to follow.
+    ///
+    /// Expects a validated scenario, as yielded by `load`.
+    pub fn create(&self, world: &mut World) -> Option<Entity> {
+        let mut factions = Factions::default();
+        let faction_ids: HashMap<&str, Faction> = self
+            .factions
+            .iter()
+            .map(|faction| {
+                let id = factions.add_faction(&faction.name, Credits(faction.credits));
+                (faction.name.as_str(), id)
+            })
+            .collect();
+        world.insert(factions);
+
+        let mut galaxy = Galaxy::default();
+        let sectors: HashMap<&str, Sector> = self
+            .sectors
+            .iter()
+            .map(|sector| {
+                let id = galaxy.add_sector(&sector.name, point(sector.position));
+                (sector.name.as_str(), id)
+            })
+            .collect();
+        world.insert(galaxy);
+
+        // Names are generated in order of definition, such that they are the same for the same seed. Stations named
+        // by the scenario keep their name, which is therefore never generated for another.
+        let mut names = world.remove::<NameGenerator>().unwrap_or_default();
+        for station in self.sectors.iter().flat_map(|def| &def.stations) {
+            if let Some(name) = &station.name {
+                names.reserve(name);
+            }
+        }
+
+        let mut named: HashMap<&str, Entity> = HashMap::new();
+        let mut bodies: HashSet<&str> = HashSet::new();
+        for def in &self.sectors {
+            let sector = sectors[def.name.as_str()];
+
+            for body in &def.bodies {
+                let (pos, orbit) = location(&body.location, &named);
+                let e = create_celestial_body(world, body.kind, sector, pos, orbit, body.radius);
+                set_name(world, e, body.name.clone());
+                named.insert(&body.name, e);
+                bodies.insert(&body.name);
+            }
+
+            for station in &def.stations {
+                let (pos, orbit) = location(&station.location, &named);
+                let recipe = station
+                    .module
+                    .as_ref()
+                    .map(|name| recipe_by_name(name).expect("scenario was validated"));
+                let owner = station
+                    .owner
+                    .as_ref()
+                    .map(|name| faction_ids[name.as_str()]);
+                let e = create_station(world, sector, pos, orbit, recipe, owner);
+                add_cargo(world, e, &station.cargo);
+
+                match &station.name {
+                    Some(name) => {
+                        set_name(world, e, name.clone());
+                        named.insert(name, e);
+                    }
+                    None => {
+                        // Unnamed stations are named after the body they orbit, if any, otherwise after their sector.
+                        let place = match &station.location {
+                            LocationDef::Orbit { center, .. }
+                                if bodies.contains(center.as_str()) =>
+                            {
+                                center
+                            }
+                            _ => &def.name,
+                        };
+                        set_name(world, e, names.station(place, station.module.as_deref()));
+                    }
+                }
+            }
+
+            for field in &def.asteroid_fields {
+                create_asteroid_field(
+                    world,
+                    sector,
+                    Position::new(point(field.position)),
+                    AsteroidField {
+                        ore: field.ore,
+                        radius: field.radius,
+                    },
+                );
+            }
+        }
+
+        for gate in &self.jump_gates {
+            create_jump_gates(
+                world,
+                (
+                    sectors[gate.a.sector.as_str()],
+                    Position::new(point(gate.a.position)),
+                ),
+                (
+                    sectors[gate.b.sector.as_str()],
+                    Position::new(point(gate.b.position)),
+                ),
+            );
+        }
+
+        let mut followed = None;
+        for def in &self.sectors {
+            let sector = sectors[def.name.as_str()];
+
+            for ship in &def.ships {
+                let pos = Position::new(point(ship.position));
+                let owner = faction_ids[ship.owner.as_str()];
+                let e = create_ship(world, &ship.class, sector, pos, owner)
+                    .expect("scenario was validated");
+                if let BehaviourDef::FlyTo(target) = &ship.behaviour {
+                    send_to(world, e, named[target.as_str()]);
+                }
+                add_cargo(world, e, &ship.cargo);
+                set_name(world, e, names.ship());
+
+                let script = ship
+                    .script
+                    .as_ref()
+                    .or_else(|| self.class_scripts.get(&ship.class));
+                if let Some(script) = script {
+                    world
+                        .write_storage::<Scripted>()
+                        .insert(e, Scripted(script.clone()))
+                        .expect("ship was just created");
+                }
+
+                followed = followed.or(Some(e));
+            }
+        }
+
+        world.insert(names);
+        followed
+    }
+}
+
+fn declare(names: &mut HashSet<String>, name: &str, context: &str) -> Result<(), ScenarioError> {
+    if names.insert(name.to_owned()) {
+        Ok(())
+    } else {
+        Err(invalid(context, format!("name {:?} is not unique", name)))
+    }
+}
+
+fn validate_position(position: (f32, f32), context: &str) -> Result<(), ScenarioError> {
+    let (x, y) = position;
+    if x < 0. || x > ARENA_WIDTH || y < 0. || y > ARENA_HEIGHT {
+        return Err(invalid(
+            context,
+            format!("position ({}, {}) lies outside the arena", x, y),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_location(
+    location: &LocationDef,
+    centers: &HashSet<&str>,
+    context: &str,
+) -> Result<(), ScenarioError> {
+    match location {
+        LocationDef::At(x, y) => validate_position((*x, *y), context),
+        LocationDef::Orbit {
+            center,
+            radius,
+            period,
+            ..
+        } => {
+            if !centers.contains(center.as_str()) {
+                return Err(invalid(
+                    context,
+                    format!(
+                        "orbit center {:?} is unknown, in another sector or not defined before this point",
+                        center
+                    ),
+                ));
+            }
+            if *radius <= 0. {
+                return Err(invalid(context, "orbit radius must be positive".to_owned()));
+            }
+            if *period == 0 {
+                return Err(invalid(context, "orbit period must be positive".to_owned()));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn validate_cargo(
+    cargo: &[(CargoType, i32)],
+    capacity: CargoUnits,
+    context: &str,
+) -> Result<(), ScenarioError> {
+    if let Some((t, amount)) = cargo.iter().find(|(_, amount)| *amount < 0) {
+        return Err(invalid(
+            context,
+            format!("cargo {:?} has negative amount {}", t, amount),
+        ));
+    }
+
+    let total = cargo
+        .iter()
+        .try_fold(0i32, |total, (_, amount)| total.checked_add(*amount))
+        .ok_or_else(|| invalid(context, "total cargo is too large".to_owned()))?;
+    if total > *capacity {
+        return Err(invalid(
+            context,
+            format!("cargo of {} exceeds capacity of {}", total, *capacity),
+        ));
+    }
+    Ok(())
+}
+
+fn set_name(world: &World, e: Entity, name: String) {
+    world
+        .write_storage::<Name>()
+        .insert(e, Name(name))
+        .expect("entity was just created");
+}
+
+fn location(location: &LocationDef, named: &HashMap<&str, Entity>) -> (Position, Option<Orbit>) {
+    match location {
+        LocationDef::At(x, y) => (Position::new(Point2::new(*x, *y)), None),
+        LocationDef::Orbit {
+            center,
+            radius,
+            period,
+            phase,
+        } => (
+            // The position of orbiting entities is derived from their orbit.
+            Position::new(Point2::new(0., 0.)),
+            Some(Orbit {
+                center: named[center.as_str()],
+                radius: *radius,
+                period: Time(*period),
+                phase: *phase,
+            }),
+        ),
+    }
+}
+
+fn add_cargo(world: &mut World, entity: Entity, items: &[(CargoType, i32)]) {
+    world
+        .write_storage::<Cargo>()
+        .get_mut(entity)
+        .expect("ships and stations have cargo")
+        .mass_change_iter(items.iter().map(|&(t, amount)| (t, CargoUnits(amount))))
+        .expect("scenario was validated");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::join::Join;
+
+    fn sector(name: &str) -> SectorDef {
+        SectorDef {
+            name: name.to_owned(),
+            ..SectorDef::default()
+        }
+    }
+
+    fn body(name: &str, location: LocationDef) -> BodyDef {
+        BodyDef {
+            name: name.to_owned(),
+            kind: CelestialBody::Planet,
+            radius: 10.,
+            location,
+        }
+    }
+
+    fn orbit(center: &str) -> LocationDef {
+        LocationDef::Orbit {
+            center: center.to_owned(),
+            radius: 100.,
+            period: 1000,
+            phase: 0.,
+        }
+    }
+
+    #[test]
+    fn orbits_stay_within_their_sector() {
+        let mut sol = sector("Sol");
+        sol.bodies.push(body("Sun", LocationDef::At(500., 500.)));
+        let mut alpha = sector("Alpha");
+        alpha.bodies.push(body("Moon", orbit("Sun")));
+        let scenario = Scenario {
+            sectors: vec![sol.clone(), alpha],
+            ..Scenario::default()
+        };
+        assert!(scenario
+            .validate(&ShipClasses::default(), &Scripts::default())
+            .is_err());
+
+        sol.bodies.push(body("Earth", orbit("Sun")));
+        let scenario = Scenario {
+            sectors: vec![sol],
+            ..Scenario::default()
+        };
+        assert!(scenario
+            .validate(&ShipClasses::default(), &Scripts::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn ship_scripts_must_exist() {
+        let mut sol = sector("Sol");
+        sol.ships.push(ShipDef {
+            class: "hauler".to_owned(),
+            owner: "Player".to_owned(),
+            position: (100., 100.),
+            behaviour: BehaviourDef::Idle,
+            cargo: Vec::new(),
+            script: Some("nearest".to_owned()),
+        });
+        let scenario = Scenario {
+            factions: vec![FactionDef {
+                name: "Player".to_owned(),
+                credits: 0,
+            }],
+            sectors: vec![sol],
+            ..Scenario::default()
+        };
+        let classes = ShipClasses::default();
+
+        let error = scenario
+            .validate(&classes, &Scripts::default())
+            .unwrap_err();
+        assert!(error.to_string().starts_with("sector \"Sol\", ship 0"));
+
+        let mut scripts = Scripts::default();
+        let ast = crate::scripting::engine()
+            .compile("fn decide(ship) { () }")
+            .unwrap();
+        scripts.insert("nearest".to_owned(), ast);
+        assert!(scenario.validate(&classes, &scripts).is_ok());
+    }
+
+    #[test]
+    fn stations_keep_their_given_name() {
+        let station = |name: Option<&str>, x| StationDef {
+            name: name.map(str::to_owned),
+            location: LocationDef::At(x, 100.),
+            owner: None,
+            module: None,
+            cargo: Vec::new(),
+        };
+        let mut sol = sector("Sol");
+        sol.stations = vec![station(None, 100.), station(Some("Sol Depot"), 300.)];
+        let scenario = Scenario {
+            sectors: vec![sol],
+            ..Scenario::default()
+        };
+
+        let mut world = World::new();
+        crate::simulation::dispatcher(false)
+            .build()
+            .setup(&mut world);
+        crate::simulation::register_components(&mut world);
+        scenario.create(&mut world);
+
+        let mut names: Vec<String> = (&world.read_storage::<Name>())
+            .join()
+            .map(|name| name.0.clone())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Sol Depot", "Sol Depot 2"]);
+    }
+
+    #[test]
+    fn overflowing_cargo_is_refused() {
+        let cargo = [(CargoType::Fuel, i32::MAX), (CargoType::Water, 1)];
+        assert!(validate_cargo(&cargo, CargoUnits(i32::MAX), "test").is_err());
+        assert!(validate_cargo(&cargo[..1], CargoUnits(i32::MAX), "test").is_ok());
+    }
+}
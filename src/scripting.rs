@@ -0,0 +1,250 @@
+use log::{info, warn};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::components::*;
+
+/// Operations a script may perform for a single decision, after which it is aborted.
+pub const MAX_OPERATIONS: u64 = 20_000;
+
+/// Engine for ship scripts.
+///
+/// Scripts only see what they are passed, cannot evaluate code of their own and are aborted once they exceed the
+/// limits on operations, recursion and the size of their data.
+pub fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1024);
+    engine.set_max_array_size(1024);
+    engine.set_max_map_size(256);
+    engine.disable_symbol("eval");
+    engine.on_print(|text| info!("[script] {}", text));
+    engine
+}
+
+/// Compiled ship scripts, by name.
+#[derive(Debug, Clone, Default)]
+pub struct Scripts {
+    scripts: HashMap<String, AST>,
+}
+
+impl Scripts {
+    /// Compile all `.rhai` files in the given directory, named after the file without its extension.
+    ///
+    /// Scripts which do not compile are logged and left out.
+    pub fn load_dir(dir: &Path) -> io::Result<Self> {
+        let engine = engine();
+        let mut scripts = HashMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "rhai")
+            {
+                continue;
+            }
+
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            match engine.compile(&fs::read_to_string(&path)?) {
+                Ok(ast) => {
+                    scripts.insert(name, ast);
+                }
+                Err(e) => warn!("Could not compile script {:?}: {}.", path, e),
+            }
+        }
+
+        Ok(Self { scripts })
+    }
+
+    pub fn insert(&mut self, name: String, ast: AST) {
+        self.scripts.insert(name, ast);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AST> {
+        self.scripts.get(name)
+    }
+}
+
+/// What a script decided an idle ship should do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Remain idle, and decide again next tick.
+    Idle,
+    FlyTo(u32),
+}
+
+/// Read-only view of a ship, passed to its script.
+#[derive(Debug, Clone)]
+pub struct ShipView {
+    pub id: u32,
+    pub sector: Sector,
+    pub position: Point2,
+    pub full: bool,
+    pub cargo: Vec<(CargoType, CargoUnits)>,
+    /// Stations in the same sector.
+    pub stations: Vec<StationView>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StationView {
+    pub id: u32,
+    pub position: Point2,
+    pub recipe: Option<&'static str>,
+    pub cargo: Vec<(CargoType, CargoUnits)>,
+}
+
+fn cargo_map(cargo: &[(CargoType, CargoUnits)]) -> Map {
+    cargo
+        .iter()
+        .filter(|&&(_, amount)| amount != CargoUnits(0))
+        .map(|&(t, amount)| (format!("{:?}", t).into(), Dynamic::from(*amount as INT)))
+        .collect()
+}
+
+impl ShipView {
+    fn to_dynamic(&self) -> Dynamic {
+        let stations: Array = self
+            .stations
+            .iter()
+            .map(|station| {
+                let distance =
+                    nalgebra_glm::distance(&self.position.coords, &station.position.coords);
+
+                let mut map = Map::new();
+                map.insert("id".into(), Dynamic::from(station.id as INT));
+                map.insert("x".into(), Dynamic::from(station.position.x as FLOAT));
+                map.insert("y".into(), Dynamic::from(station.position.y as FLOAT));
+                map.insert("distance".into(), Dynamic::from(distance as FLOAT));
+                map.insert(
+                    "recipe".into(),
+                    Dynamic::from(station.recipe.unwrap_or_default().to_owned()),
+                );
+                map.insert("cargo".into(), Dynamic::from(cargo_map(&station.cargo)));
+                Dynamic::from(map)
+            })
+            .collect();
+
+        let mut map = Map::new();
+        map.insert("id".into(), Dynamic::from(self.id as INT));
+        map.insert("sector".into(), Dynamic::from(self.sector.0 as INT));
+        map.insert("x".into(), Dynamic::from(self.position.x as FLOAT));
+        map.insert("y".into(), Dynamic::from(self.position.y as FLOAT));
+        map.insert("full".into(), Dynamic::from(self.full));
+        map.insert("cargo".into(), Dynamic::from(cargo_map(&self.cargo)));
+        map.insert("stations".into(), Dynamic::from(stations));
+        Dynamic::from(map)
+    }
+}
+
+/// Call the `decide` function of the script, which receives the ship and returns an action such as
+/// `#{ action: "fly_to", target: 3 }` or `#{ action: "idle" }`.
+pub fn decide(engine: &Engine, script: &AST, ship: &ShipView) -> Result<Action, String> {
+    let result: Dynamic = engine
+        .call_fn(&mut Scope::new(), script, "decide", (ship.to_dynamic(),))
+        .map_err(|e| e.to_string())?;
+
+    if result.is_unit() {
+        return Ok(Action::Idle);
+    }
+
+    let map = result
+        .try_cast::<Map>()
+        .ok_or("expected an action such as #{ action: \"idle\" }")?;
+    let action = map
+        .get("action")
+        .and_then(|action| action.clone().into_string().ok())
+        .ok_or("action must be a string")?;
+
+    match action.as_str() {
+        "idle" => Ok(Action::Idle),
+        "fly_to" => {
+            let target = map
+                .get("target")
+                .and_then(|target| target.as_int().ok())
+                .ok_or("fly_to requires the id of the target")?;
+            u32::try_from(target)
+                .map(Action::FlyTo)
+                .map_err(|_| format!("{} is not the id of an entity", target))
+        }
+        _ => Err(format!("unknown action {:?}", action)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ship() -> ShipView {
+        let station = |id, x| StationView {
+            id,
+            position: Point2::new(x, 0.),
+            recipe: None,
+            cargo: Vec::new(),
+        };
+
+        ShipView {
+            id: 0,
+            sector: Sector(0),
+            position: Point2::new(0., 0.),
+            full: false,
+            cargo: Vec::new(),
+            stations: vec![station(1, 300.), station(2, 100.), station(3, 200.)],
+        }
+    }
+
+    #[test]
+    fn script_decides_action() {
+        let engine = engine();
+        let script = engine
+            .compile(
+                r#"
+                fn decide(ship) {
+                    let best = ship.stations[0];
+                    for station in ship.stations {
+                        if station.distance < best.distance {
+                            best = station;
+                        }
+                    }
+                    #{ action: "fly_to", target: best.id }
+                }
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(decide(&engine, &script, &ship()), Ok(Action::FlyTo(2)));
+    }
+
+    #[test]
+    fn out_of_range_target_is_refused() {
+        let engine = engine();
+        let decision = |target: &str| {
+            let source = format!(
+                "fn decide(ship) {{ #{{ action: \"fly_to\", target: {} }} }}",
+                target
+            );
+            decide(&engine, &engine.compile(&source).unwrap(), &ship())
+        };
+
+        assert_eq!(decision("5"), Ok(Action::FlyTo(5)));
+        assert!(decision("-1").is_err());
+        assert!(decision("4294967301").is_err());
+    }
+
+    #[test]
+    fn endless_script_is_aborted() {
+        let engine = engine();
+        let script = engine.compile("fn decide(ship) { loop { } }").unwrap();
+
+        assert!(decide(&engine, &script, &ship()).is_err());
+    }
+}
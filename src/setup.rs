@@ -0,0 +1,110 @@
+use amethyst::ecs::{Entity, World, WorldExt};
+use std::path::PathBuf;
+
+use traders::api::ApiServer;
+use traders::components::*;
+use traders::names::NameGenerator;
+use traders::profiling;
+use traders::recipes::Recipes;
+use traders::replay::Session;
+use traders::savegame::{EntityRef, SaveGame, SavedView};
+use traders::scenario::Scenario;
+use traders::scripting::Scripts;
+use traders::ships::ShipClasses;
+use traders::simulation::SimulationConfig;
+use traders::statistics::Statistics;
+
+/// Where the universe comes from.
+#[derive(Debug)]
+pub enum Start {
+    Scenario(Scenario),
+    Save(SaveGame),
+}
+
+/// Everything chosen on the command line which is common to the game and the headless mode.
+#[derive(Debug)]
+pub struct Setup {
+    pub config: SimulationConfig,
+    pub start: Start,
+    /// File the scenario was read from, if any, such that it can be reloaded.
+    pub scenario_path: Option<PathBuf>,
+    pub session: Session,
+    /// Scripts which ships may be steered by.
+    pub scripts: Scripts,
+    pub recipes: Recipes,
+    pub ship_classes: ShipClasses,
+    /// Where to write the statistics when exiting.
+    pub stats: Option<PathBuf>,
+    /// Where to write the profile when exiting.
+    pub profile: Option<PathBuf>,
+    /// Server for the control API, if enabled.
+    pub api: Option<ApiServer>,
+}
+
+impl Setup {
+    /// Create the initial universe, and start recording or replaying.
+    ///
+    /// Yields the view to start with, if any, along with the entities it refers to.
+    pub fn begin(&self, world: &mut World) -> amethyst::Result<Option<(SavedView, Vec<Entity>)>> {
+        world.insert(self.scripts.clone());
+        world.insert(self.recipes.clone());
+        world.insert(self.ship_classes.clone());
+
+        let view = match &self.start {
+            Start::Scenario(scenario) => {
+                world.insert(NameGenerator::new(self.config.seed));
+                let followed = scenario.create(world);
+                self.session.begin(
+                    world,
+                    self.config.seed,
+                    scenario,
+                    &self.recipes,
+                    &self.ship_classes,
+                );
+
+                followed.map(|ship| {
+                    let view = SavedView {
+                        zoom: 1.0,
+                        sector: *world
+                            .read_storage::<Sector>()
+                            .get(ship)
+                            .expect("ships are always in a sector"),
+                        follow: Some(EntityRef(0)),
+                    };
+                    (view, vec![ship])
+                })
+            }
+            Start::Save(save) => {
+                let view = save.view.clone();
+                let entities = save.clone().restore(world)?;
+                view.map(|view| (view, entities))
+            }
+        };
+
+        self.recipes.apply(world);
+        self.ship_classes.apply(world);
+        Ok(view)
+    }
+
+    /// Answer the requests to the control API received so far.
+    pub fn process_requests(&self, world: &mut World) {
+        if let Some(api) = &self.api {
+            api.process(world);
+        }
+    }
+
+    /// Finish recording or replaying, and write the requested reports.
+    pub fn finish(&self, world: &mut World) -> amethyst::Result<()> {
+        self.session.finish(world)?;
+
+        if let Some(path) = &self.stats {
+            world.read_resource::<Statistics>().write(path)?;
+        }
+
+        if let Some(path) = &self.profile {
+            profiling::write_report(world, path)?;
+        }
+
+        Ok(())
+    }
+}
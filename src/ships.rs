@@ -0,0 +1,225 @@
+use amethyst::ecs::{join::Join, World, WorldExt};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::*;
+
+/// Stats shared by all ships of a class, as given in `assets/ships.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipClass {
+    /// Index of the sprite in `assets/texture/ships.ron`.
+    pub sprite: usize,
+    /// Width and height.
+    pub hitbox: (f32, f32),
+    pub capacity: i32,
+    /// Distance covered per tick at full speed.
+    pub max_speed: f32,
+    /// Acceleration, in units per tick per tick. Ships do not accelerate yet, but reach their maximum speed at once.
+    pub thrust: f32,
+    /// Angle turned per tick. Ships do not turn yet, but head straight for their destination.
+    pub turn_rate: f32,
+    /// Fuel carried for the ship's own engines, apart from its cargo.
+    pub fuel_tank: i32,
+    pub cost: i64,
+}
+
+#[derive(Debug)]
+pub enum ShipClassError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, ron::de::Error),
+    Invalid { class: String, message: String },
+}
+
+impl fmt::Display for ShipClassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShipClassError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ShipClassError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ShipClassError::Invalid { class, message } => {
+                write!(f, "ship class {:?}: {}", class, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShipClassError {}
+
+/// Catalogue of ship classes, by name.
+///
+/// It is serialized as the map of classes, such that recordings can carry the catalogue they were made with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, ShipClass>",
+    into = "BTreeMap<String, ShipClass>"
+)]
+pub struct ShipClasses {
+    classes: BTreeMap<String, ShipClass>,
+}
+
+/// The catalogue shipped with the game, such that tools and tests need not read it.
+impl Default for ShipClasses {
+    fn default() -> Self {
+        let classes = ron::de::from_str(include_str!("../assets/ships.ron"))
+            .expect("built-in ship classes are valid");
+        Self { classes }
+    }
+}
+
+impl ShipClasses {
+    /// Read and validate the catalogue.
+    pub fn load(path: &Path) -> Result<Self, ShipClassError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ShipClassError::Io(path.to_owned(), e))?;
+        let classes: BTreeMap<String, ShipClass> =
+            ron::de::from_str(&contents).map_err(|e| ShipClassError::Parse(path.to_owned(), e))?;
+        Self::try_from(classes)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ShipClass> {
+        self.classes.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ShipClass)> + '_ {
+        self.classes
+            .iter()
+            .map(|(name, class)| (name.as_str(), class))
+    }
+
+    /// Give all ships the current stats of their class.
+    ///
+    /// Ships whose cargo no longer fits keep their old capacity, and ships of classes which no longer exist are left
+    /// as they are.
+    pub fn apply(&self, world: &World) {
+        let (entities, mut stats, mut hitbox, mut cargo) = (
+            world.entities(),
+            world.write_storage::<ShipStats>(),
+            world.write_storage::<Hitbox>(),
+            world.write_storage::<Cargo>(),
+        );
+
+        for (e, stats, hitbox, cargo) in (
+            &entities,
+            &mut stats,
+            (&mut hitbox).maybe(),
+            (&mut cargo).maybe(),
+        )
+            .join()
+        {
+            let class = match self.get(&stats.class) {
+                Some(class) => class,
+                None => continue,
+            };
+
+            *stats = ShipStats::new(&stats.class, class);
+            if let Some(hitbox) = hitbox {
+                *hitbox = Hitbox::new(Translation2::new(class.hitbox.0, class.hitbox.1));
+            }
+            if let Some(cargo) = cargo {
+                if cargo.set_capacity(CargoUnits(class.capacity)).is_err() {
+                    warn!(
+                        "Cargo of {:?} exceeds the new capacity of its class {}.",
+                        e, stats.class
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, ShipClass>> for ShipClasses {
+    type Error = ShipClassError;
+
+    fn try_from(classes: BTreeMap<String, ShipClass>) -> Result<Self, Self::Error> {
+        for (name, class) in &classes {
+            validate(class).map_err(|message| ShipClassError::Invalid {
+                class: name.clone(),
+                message: message.to_owned(),
+            })?;
+        }
+
+        Ok(Self { classes })
+    }
+}
+
+impl From<ShipClasses> for BTreeMap<String, ShipClass> {
+    fn from(classes: ShipClasses) -> Self {
+        classes.classes
+    }
+}
+
+fn validate(class: &ShipClass) -> Result<(), &'static str> {
+    if class.hitbox.0 <= 0. || class.hitbox.1 <= 0. {
+        return Err("hitbox must be positive");
+    }
+    if class.capacity < 0 || class.cost < 0 {
+        return Err("capacity and cost must not be negative");
+    }
+    let positive = |value: f32| value.is_finite() && value > 0.;
+    if !positive(class.max_speed) || !positive(class.thrust) || !positive(class.turn_rate) {
+        return Err("maximum speed, thrust and turn rate must be finite and positive");
+    }
+    if class.fuel_tank <= 0 {
+        return Err("fuel tank must be positive");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_classes_are_valid() {
+        let classes = ShipClasses::default();
+        let names: Vec<&str> = classes.iter().map(|(name, _)| name).collect();
+
+        assert_eq!(names, ["courier", "freighter", "hauler", "miner"]);
+        assert!(classes.iter().all(|(_, class)| validate(class).is_ok()));
+    }
+
+    #[test]
+    fn classes_round_trip_and_are_validated() {
+        let classes = ShipClasses::default();
+        let serialized = ron::ser::to_string(&classes).unwrap();
+        assert_eq!(
+            ron::de::from_str::<ShipClasses>(&serialized).unwrap(),
+            classes
+        );
+
+        let invalid = serialized.replace("max_speed:2", "max_speed:0");
+        assert_ne!(invalid, serialized);
+        assert!(ron::de::from_str::<ShipClasses>(&invalid).is_err());
+    }
+
+    #[test]
+    fn stats_must_be_finite_and_positive() {
+        let courier = ShipClasses::default().get("courier").cloned().unwrap();
+        assert!(validate(&courier).is_ok());
+
+        let invalid = [
+            ShipClass {
+                thrust: 0.,
+                ..courier.clone()
+            },
+            ShipClass {
+                turn_rate: f32::NAN,
+                ..courier.clone()
+            },
+            ShipClass {
+                max_speed: f32::INFINITY,
+                ..courier.clone()
+            },
+            ShipClass {
+                fuel_tank: 0,
+                ..courier
+            },
+        ];
+        assert!(invalid.iter().all(|class| validate(class).is_err()));
+    }
+}
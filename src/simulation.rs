@@ -0,0 +1,185 @@
+use amethyst::ecs::{Dispatcher, DispatcherBuilder, World, WorldExt};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::components::*;
+use crate::events::EventCounts;
+use crate::profiling::{Profiler, WithProfiled};
+use crate::statistics::Statistics;
+use crate::systems;
+
+/// Real time covered by a single simulation tick.
+pub const TICK: Duration = Duration::from_millis(20);
+
+/// Upper bound on the ticks simulated per frame, such that a slow machine does not fall further and further behind.
+pub const MAX_TICKS_PER_FRAME: u32 = 10;
+
+/// Real time per frame which may be spent on simulating at maximum speed.
+pub const MAX_FRAME_BUDGET: Duration = Duration::from_millis(15);
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct SimulationConfig {
+    /// Seed for all randomness, such that runs can be reproduced.
+    pub seed: u64,
+}
+
+/// Systems advancing the simulation by a single tick, independent of any rendering.
+///
+/// When profiling, the wall time of each system is measured into the `Profiler` resource, at a small cost per run.
+pub fn dispatcher<'a, 'b>(profile: bool) -> DispatcherBuilder<'a, 'b> {
+    DispatcherBuilder::new()
+        .with_profiled_if(profile, systems::ApplyCommands, "apply_commands", &[])
+        .with_profiled_if(
+            profile,
+            systems::IncrementTime,
+            "increment_time",
+            &["apply_commands"],
+        )
+        .with_profiled_if(profile, systems::Orbiting, "orbiting", &["increment_time"])
+        .with_profiled_if(
+            profile,
+            systems::scripting::RunScripts::default(),
+            "run_scripts",
+            &["apply_commands"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::behaviour::RunBehaviourTrees,
+            "behaviour_trees",
+            &["apply_commands"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::behaviour::FlyTo,
+            "behaviour_fly_to",
+            &["orbiting", "run_scripts", "behaviour_trees"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::behaviour::FollowPath,
+            "behaviour_follow_path",
+            &["behaviour_fly_to"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::behaviour::Jump,
+            "behaviour_jump",
+            &["behaviour_follow_path"],
+        )
+        .with_profiled_if(profile, systems::Movement, "movement", &["behaviour_jump"])
+        .with_profiled_if(profile, systems::Rotation, "rotation", &["behaviour_jump"])
+        .with_profiled_if(profile, systems::Fabrication, "fabrication", &[])
+        .with_profiled_if(
+            profile,
+            systems::events::LogEvents::default(),
+            "log_events",
+            &["behaviour_fly_to", "behaviour_jump", "fabrication"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::events::CountEvents::default(),
+            "count_events",
+            &["behaviour_fly_to", "behaviour_jump", "fabrication"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::statistics::SampleStatistics::default(),
+            "sample_statistics",
+            &["increment_time", "fabrication"],
+        )
+        .with_profiled_if(
+            profile,
+            systems::HashState,
+            "hash_state",
+            &["movement", "rotation", "fabrication"],
+        )
+}
+
+/// Register the simulation components which are not already registered by any of the systems.
+pub fn register_components(world: &mut World) {
+    world.register::<Trader>();
+    world.register::<ShipStats>();
+    world.register::<Station>();
+    world.register::<Hitbox>();
+    world.register::<CelestialBody>();
+    world.register::<AsteroidField>();
+    world.register::<Faction>();
+    world.register::<Name>();
+}
+
+/// Forget the statistics, event counts and timings of the previous run, once the universe was replaced by another.
+pub fn reset_reports(world: &mut World) {
+    world.insert(EventCounts::default());
+    if let Some(mut statistics) = world.try_fetch_mut::<Statistics>() {
+        statistics.clear();
+    }
+    if let Some(mut profiler) = world.try_fetch_mut::<Profiler>() {
+        profiler.reset();
+    }
+}
+
+/// Advance the simulation by a single tick.
+pub fn tick(dispatcher: &mut Dispatcher, world: &mut World) {
+    dispatcher.dispatch(world);
+    world.maintain();
+
+    if let Some(mut profiler) = world.try_fetch_mut::<Profiler>() {
+        profiler.tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::*;
+    use amethyst::ecs::{Builder, Entity};
+
+    fn run(seed: u64, ticks: u32) -> Vec<(f32, f32)> {
+        let mut world = World::new();
+        let mut dispatcher = dispatcher(false).build();
+        dispatcher.setup(&mut world);
+        world.insert(CurrentTime::default());
+        world.insert(SimRng::seed_from_u64(seed));
+
+        for &(x, y) in &[(100., 100.), (800., 300.), (400., 900.), (500., 500.)] {
+            world
+                .create_entity()
+                .with(Station)
+                .with(Sector(0))
+                .with(Position::new(Point2::new(x, y)))
+                .with(Obstacle::new(30.))
+                .build();
+        }
+
+        let ships: Vec<Entity> = (0..5)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .with(Sector(0))
+                    .with(Position::new(Point2::new(100. * i as f32, 600.)))
+                    .with(Velocity::default())
+                    .with(ShipBehaviour::Idle)
+                    .with(Brain::new("trader"))
+                    .build()
+            })
+            .collect();
+
+        for _ in 0..ticks {
+            tick(&mut dispatcher, &mut world);
+        }
+
+        let pos = world.read_storage::<Position>();
+        ships
+            .iter()
+            .map(|&e| {
+                let pos = pos.get(e).unwrap();
+                (pos.x, pos.y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_yields_same_world() {
+        assert_eq!(run(42, 3000), run(42, 3000));
+    }
+}
@@ -0,0 +1,173 @@
+use enum_map::EnumMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use crate::components::*;
+
+/// Amounts per cargo type.
+pub type CargoTotals = EnumMap<CargoType, CargoUnits>;
+
+/// State of the economy at a single point in time.
+#[derive(Debug, Clone, Serialize)]
+pub struct Sample {
+    pub time: Time,
+    /// Units stored in stations.
+    pub in_stations: CargoTotals,
+    /// Units carried by ships.
+    pub in_transit: CargoTotals,
+    /// Units fabricated since the previous sample.
+    pub produced: CargoTotals,
+    /// Units used up as ingredients since the previous sample.
+    pub consumed: CargoTotals,
+}
+
+/// Samples of the economy taken at a fixed interval, keeping only the most recent ones.
+///
+/// Ships do not trade yet, hence there are no prices or trade volumes to sample.
+#[derive(Debug)]
+pub struct Statistics {
+    /// Ticks between samples, at least one.
+    interval: u32,
+    capacity: usize,
+    samples: VecDeque<Sample>,
+    /// Units fabricated since the last sample.
+    pub produced: CargoTotals,
+    /// Units used up as ingredients since the last sample.
+    pub consumed: CargoTotals,
+}
+
+impl Default for Statistics {
+    /// Sample every second, keeping an hour worth of samples.
+    fn default() -> Self {
+        Self::new(50, 3600)
+    }
+}
+
+impl Statistics {
+    pub fn new(interval: u32, capacity: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            samples: VecDeque::with_capacity(capacity.max(1)),
+            produced: CargoTotals::default(),
+            consumed: CargoTotals::default(),
+        }
+    }
+
+    /// Ticks between samples.
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Drop all samples and what was counted towards the next one.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.produced = CargoTotals::default();
+        self.consumed = CargoTotals::default();
+    }
+
+    /// Samples from oldest to newest.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    /// Write all samples as JSON if the path ends in `.json`, and as CSV otherwise.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        match path.extension() {
+            Some(extension) if extension == "json" => self.write_json(path),
+            _ => self.write_csv(path),
+        }
+    }
+
+    /// Write all samples as a JSON array, with the amounts of each sample by cargo type.
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        let samples: Vec<&Sample> = self.samples.iter().collect();
+        let out = serde_json::to_string_pretty(&samples)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, out)
+    }
+
+    /// Write all samples as CSV, with a row per sample and cargo type.
+    pub fn write_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut out = String::from("time,cargo,in_stations,in_transit,produced,consumed\n");
+        for sample in &self.samples {
+            for (t, in_stations) in sample.in_stations.iter() {
+                out.push_str(&format!(
+                    "{},{:?},{},{},{},{}\n",
+                    *sample.time,
+                    t,
+                    **in_stations,
+                    *sample.in_transit[t],
+                    *sample.produced[t],
+                    *sample.consumed[t]
+                ));
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: u32) -> Sample {
+        Sample {
+            time: Time(time),
+            in_stations: CargoTotals::default(),
+            in_transit: CargoTotals::default(),
+            produced: CargoTotals::default(),
+            consumed: CargoTotals::default(),
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_samples() {
+        for &capacity in &[0, 1, 3] {
+            let mut statistics = Statistics::new(50, capacity);
+            for time in 0..10 {
+                statistics.push(sample(time));
+            }
+
+            let kept = capacity.max(1);
+            let times: Vec<u32> = statistics.samples().map(|s| *s.time).collect();
+            assert_eq!(times, (10 - kept as u32..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn interval_is_at_least_a_tick() {
+        assert_eq!(Statistics::new(0, 10).interval(), 1);
+        assert_eq!(Statistics::new(50, 10).interval(), 50);
+    }
+
+    #[test]
+    fn samples_serialize_by_cargo_type() {
+        let mut sample = sample(50);
+        sample.produced[CargoType::Fuel] = CargoUnits(3);
+
+        let json = serde_json::to_value(&sample).unwrap();
+        assert_eq!(json["time"], 50);
+        assert_eq!(json["produced"]["Fuel"], 3);
+    }
+}
@@ -1,61 +1,317 @@
+use crate::behaviour_tree::{BehaviourTrees, Context};
 use crate::components::*;
-use amethyst::ecs::{
-    join::Join,
-    prelude::{ReadStorage, System, WriteStorage},
-    Entities, Entity,
+use crate::events::SimEvent;
+use crate::galaxy::Galaxy;
+use crate::navigation::{Circle, Navigation};
+use crate::resources::{CurrentTime, SimRng};
+use amethyst::{
+    ecs::{
+        join::Join,
+        prelude::{Read, ReadExpect, ReadStorage, System, Write, WriteExpect, WriteStorage},
+        Entities, Entity,
+    },
+    shrev::EventChannel,
 };
 use std::ops::{Deref, DerefMut};
 
-pub struct Idle;
+/// Tick the behaviour trees of all ships which are not steered by a script.
+pub struct RunBehaviourTrees;
 
-impl<'a> System<'a> for Idle {
+impl<'a> System<'a> for RunBehaviourTrees {
     type SystemData = (
         Entities<'a>,
+        ReadExpect<'a, CurrentTime>,
+        WriteExpect<'a, SimRng>,
+        Read<'a, BehaviourTrees>,
         ReadStorage<'a, Station>,
+        ReadStorage<'a, Scripted>,
+        ReadStorage<'a, Cargo>,
+        WriteStorage<'a, Brain>,
         WriteStorage<'a, ShipBehaviour>,
     );
 
-    fn run(&mut self, (entities, station, mut behaviour): Self::SystemData) {
-        use rand::seq::IteratorRandom;
-        let mut rng = rand::thread_rng();
+    fn run(
+        &mut self,
+        (entities, time, mut rng, trees, station, scripted, cargo, mut brain, mut behaviour): Self::SystemData,
+    ) {
         let stations: Vec<Entity> = (&entities, &station).join().map(|(e, _)| e).collect();
 
-        for behaviour in (&mut behaviour).join() {
-            if let ShipBehaviour::Idle = behaviour {
-                let station = stations.iter().choose(&mut rng).unwrap();
-                *behaviour = ShipBehaviour::FlyTo(*station);
-            }
+        // Ships draw in order of their entity, keeping the simulation deterministic.
+        for (brain, behaviour, cargo, _) in
+            (&mut brain, &mut behaviour, (&cargo).maybe(), !&scripted).join()
+        {
+            let tree = match trees.get(&brain.tree) {
+                Some(tree) => tree,
+                None => continue,
+            };
+
+            let mut ctx = Context {
+                entities: &entities,
+                time: time.0,
+                rng: &mut rng,
+                stations: &stations,
+                blackboard: &mut brain.blackboard,
+                behaviour,
+                cargo,
+            };
+            tree.tick(&mut ctx);
+        }
+    }
+}
+
+/// Distance covered in a single tick by ships without a class.
+const MAX_SPEED: f32 = 1.;
+
+fn speed_of(stats: Option<&ShipStats>) -> f32 {
+    stats.map_or(MAX_SPEED, |stats| stats.max_speed)
+}
+
+/// Velocity to approach the destination at the given maximum speed.
+///
+/// Yields None when the destination has been reached.
+fn approach(from: &Point2, to: &Point2, max_speed: f32) -> Option<Translation2> {
+    let vec = to - from;
+    let len = nalgebra_glm::length(&vec);
+
+    if len > 0.00001 {
+        let new_len = f32::min(max_speed, len);
+        Some(Translation2::from(vec.scale(new_len / len)))
+    } else {
+        None
+    }
+}
+
+/// Where to meet the target, accounting for its orbit.
+fn intercept(
+    from: &Point2,
+    max_speed: f32,
+    target: Entity,
+    time: Time,
+    orbit: &ReadStorage<Orbit>,
+    pos: &ReadStorage<Position>,
+) -> Option<Point2> {
+    let mut res = *pos.get(target)?.deref();
+
+    // Refine the estimate of the flight duration a few times.
+    if orbit.get(target).is_some() {
+        for _ in 0..3 {
+            let ticks = f32::ceil(nalgebra_glm::length(&(res - from)) / max_speed) as u32;
+            res = position_at(target, time + Time(ticks), orbit, pos)?;
         }
     }
+
+    Some(res)
+}
+
+fn circle(pos: &Position, obstacle: &Obstacle) -> Circle {
+    Circle {
+        center: *pos.deref(),
+        radius: *obstacle.deref(),
+    }
 }
 
 pub struct FlyTo;
 
 impl<'a> System<'a> for FlyTo {
     type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CurrentTime>,
         ReadStorage<'a, Position>,
+        ReadStorage<'a, Obstacle>,
+        ReadStorage<'a, Orbit>,
+        ReadStorage<'a, Sector>,
+        ReadStorage<'a, JumpGate>,
+        ReadStorage<'a, ShipStats>,
+        Write<'a, Navigation>,
+        Write<'a, Galaxy>,
+        Write<'a, EventChannel<SimEvent>>,
         WriteStorage<'a, ShipBehaviour>,
         WriteStorage<'a, Velocity>,
     );
 
-    fn run(&mut self, (pos, mut behaviour, mut vel): Self::SystemData) {
-        for (&our_pos, behaviour, vel) in (&pos, &mut behaviour, &mut vel).join() {
+    fn run(
+        &mut self,
+        (
+            entities,
+            time,
+            pos,
+            obstacle,
+            orbit,
+            sector,
+            gate,
+            stats,
+            mut navigation,
+            mut galaxy,
+            mut events,
+            mut behaviour,
+            mut vel,
+        ): Self::SystemData,
+    ) {
+        // Orbiting bodies move continuously, hence they are told apart from the others.
+        navigation.update(
+            (&entities, &pos, &sector, &obstacle, !&orbit)
+                .join()
+                .map(|(e, pos, &sector, obstacle, _)| (e, sector, circle(pos, obstacle)))
+                .collect(),
+            (&entities, &pos, &sector, &obstacle, &orbit)
+                .join()
+                .map(|(e, pos, &sector, obstacle, _)| (e, sector, circle(pos, obstacle)))
+                .collect(),
+        );
+
+        galaxy.update(
+            (&entities, &sector, &gate)
+                .join()
+                .filter_map(|(e, &s, gate)| sector.get(gate.destination).map(|&d| (e, s, d)))
+                .collect(),
+        );
+
+        for (e, &our_pos, &our_sector, stats, behaviour, vel) in (
+            &entities,
+            &pos,
+            &sector,
+            (&stats).maybe(),
+            &mut behaviour,
+            &mut vel,
+        )
+            .join()
+        {
             if let ShipBehaviour::FlyTo(target) = behaviour {
-                if let Some(&target_pos) = pos.get(*target) {
-                    let vec = *target_pos.deref() - *our_pos.deref();
-                    let len = nalgebra_glm::length(&vec);
-
-                    let trans = if len > 0.00001 {
-                        let new_len = f32::min(1., len);
-                        Translation2::from(vec.scale(new_len / len))
-                    } else {
+                let target = *target;
+                let max_speed = speed_of(stats);
+
+                // Head for the next jump gate while the target lies in another sector.
+                let heading = match sector.get(target) {
+                    Some(&s) if s == our_sector => Some(target),
+                    Some(&s) => galaxy
+                        .route(our_sector, s)
+                        .and_then(|route| route.first().copied()),
+                    None => None,
+                };
+
+                let heading = match heading {
+                    Some(heading) => heading,
+                    None => {
                         *behaviour = ShipBehaviour::Idle;
-                        Translation2::new(0., 0.)
-                    };
+                        *vel = Velocity::default();
+                        continue;
+                    }
+                };
+
+                if let Some(heading_pos) =
+                    intercept(&our_pos, max_speed, heading, time.0, &orbit, &pos)
+                {
+                    // Fly straight unless something is in the way, or no way around it exists.
+                    if !navigation.is_clear(our_sector, &our_pos, &heading_pos, heading) {
+                        if let Some(path) =
+                            navigation.find_path(our_sector, &our_pos, heading, &heading_pos)
+                        {
+                            *behaviour = ShipBehaviour::FollowPath(target, path.into());
+                            continue;
+                        }
+                    }
+
+                    match approach(&our_pos, &heading_pos, max_speed) {
+                        Some(trans) => *vel.deref_mut() = trans,
+                        None => {
+                            *vel = Velocity::default();
+                            *behaviour = if heading == target {
+                                events.single_write(SimEvent::Arrived { ship: e, target });
+                                ShipBehaviour::Idle
+                            } else {
+                                ShipBehaviour::Jump(heading, target)
+                            };
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct FollowPath;
+
+impl<'a> System<'a> for FollowPath {
+    type SystemData = (
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, ShipStats>,
+        WriteStorage<'a, ShipBehaviour>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (pos, stats, mut behaviour, mut vel): Self::SystemData) {
+        for (&our_pos, stats, behaviour, vel) in
+            (&pos, (&stats).maybe(), &mut behaviour, &mut vel).join()
+        {
+            if let ShipBehaviour::FollowPath(target, waypoints) = behaviour {
+                let target = *target;
+                let max_speed = speed_of(stats);
+                while waypoints
+                    .front()
+                    .map_or(false, |w| approach(&our_pos, w, max_speed).is_none())
+                {
+                    waypoints.pop_front();
+                }
+                let trans = waypoints
+                    .front()
+                    .and_then(|w| approach(&our_pos, w, max_speed));
 
+                if pos.get(target).is_none() {
+                    *behaviour = ShipBehaviour::Idle;
+                    *vel = Velocity::default();
+                } else if let Some(trans) = trans {
                     *vel.deref_mut() = trans;
+                } else {
+                    // Let FlyTo take care of the final approach.
+                    *behaviour = ShipBehaviour::FlyTo(target);
                 }
             }
         }
     }
 }
+
+pub struct Jump;
+
+impl<'a> System<'a> for Jump {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, JumpGate>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Sector>,
+        WriteStorage<'a, ShipBehaviour>,
+        Write<'a, EventChannel<SimEvent>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, gate, mut pos, mut sector, mut behaviour, mut events): Self::SystemData,
+    ) {
+        for (e, behaviour) in (&entities, &mut behaviour).join() {
+            if let ShipBehaviour::Jump(via, target) = behaviour {
+                let target = *target;
+
+                // Materialize at the paired gate on the other side.
+                let destination = gate.get(*via).map(|gate| gate.destination);
+                let arrival = destination.and_then(|d| Some((*pos.get(d)?, *sector.get(d)?)));
+
+                *behaviour = match arrival {
+                    Some((new_pos, new_sector)) => {
+                        if let Some(pos) = pos.get_mut(e) {
+                            *pos = new_pos;
+                        }
+                        if let Some(sector) = sector.get_mut(e) {
+                            events.single_write(SimEvent::Jumped {
+                                ship: e,
+                                from: *sector,
+                                to: new_sector,
+                            });
+                            *sector = new_sector;
+                        }
+                        ShipBehaviour::FlyTo(target)
+                    }
+                    None => ShipBehaviour::Idle,
+                };
+            }
+        }
+    }
+}
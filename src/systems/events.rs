@@ -0,0 +1,87 @@
+use amethyst::{
+    ecs::{
+        prelude::{Read, ReadStorage, System, SystemData, Write},
+        World,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use log::debug;
+
+use crate::components::Name;
+use crate::events::*;
+use crate::names;
+
+/// Log all simulation events, referring to entities by name.
+#[derive(Default)]
+pub struct LogEvents {
+    reader: Option<ReaderId<SimEvent>>,
+}
+
+impl<'a> System<'a> for LogEvents {
+    type SystemData = (Read<'a, EventChannel<SimEvent>>, ReadStorage<'a, Name>);
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<SimEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, (events, name): Self::SystemData) {
+        let describe = |e| names::describe(e, name.get(e));
+
+        let reader = self.reader.as_mut().expect("reader is registered in setup");
+        for event in events.read(reader) {
+            match *event {
+                SimEvent::Arrived { ship, target } => {
+                    debug!("{} arrived at {}.", describe(ship), describe(target))
+                }
+                SimEvent::Jumped { ship, from, to } => debug!(
+                    "{} jumped from sector {} to {}.",
+                    describe(ship),
+                    from.0,
+                    to.0
+                ),
+                SimEvent::FabricationStarted { station, recipe } => {
+                    debug!("{} started fabricating {}.", describe(station), recipe)
+                }
+                SimEvent::FabricationCompleted { station, recipe } => {
+                    debug!("{} fabricated {}.", describe(station), recipe)
+                }
+            }
+        }
+    }
+}
+
+/// Keep count of the simulation events, for statistics.
+#[derive(Default)]
+pub struct CountEvents {
+    reader: Option<ReaderId<SimEvent>>,
+}
+
+impl<'a> System<'a> for CountEvents {
+    type SystemData = (Read<'a, EventChannel<SimEvent>>, Write<'a, EventCounts>);
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<SimEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, (events, mut counts): Self::SystemData) {
+        let reader = self.reader.as_mut().expect("reader is registered in setup");
+        for event in events.read(reader) {
+            match event {
+                SimEvent::Arrived { .. } => counts.arrivals += 1,
+                SimEvent::Jumped { .. } => counts.jumps += 1,
+                SimEvent::FabricationStarted { .. } => counts.fabrications_started += 1,
+                SimEvent::FabricationCompleted { .. } => counts.fabrications_completed += 1,
+            }
+        }
+    }
+}
@@ -1,20 +1,120 @@
 pub mod behaviour;
+pub mod events;
+pub mod scripting;
+pub mod statistics;
 
 use amethyst::{
-    core::{math, transform::Transform},
     ecs::{
         join::Join,
-        prelude::{ReadExpect, ReadStorage, System, WriteExpect, WriteStorage},
+        prelude::{ReadExpect, ReadStorage, System, Write, WriteExpect, WriteStorage},
+        Entities, Entity,
     },
-    renderer::Camera,
-    ui::UiTransform,
-    window::ScreenDimensions,
+    shrev::EventChannel,
 };
 use std::ops::{Deref, DerefMut};
 
+use crate::commands::*;
 use crate::components::*;
+use crate::events::SimEvent;
+use crate::replay::{self, Recording, ReplayState};
 use crate::resources::*;
 
+/// Apply the commands issued by the player, or those of the recording instead when replaying.
+pub struct ApplyCommands;
+
+impl<'a> System<'a> for ApplyCommands {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CurrentTime>,
+        Write<'a, PendingCommands>,
+        Option<Write<'a, ReplayState>>,
+        Option<Write<'a, Recording>>,
+        WriteStorage<'a, ShipBehaviour>,
+        WriteStorage<'a, Brain>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, mut pending, replay, recording, mut behaviour, mut brain): Self::SystemData,
+    ) {
+        let commands = match replay {
+            Some(mut replay) => {
+                pending.0.clear();
+                replay.due(time.0)
+            }
+            None => std::mem::take(&mut pending.0),
+        };
+
+        if let Some(mut recording) = recording {
+            recording
+                .commands
+                .extend(commands.iter().map(|command| (time.0, command.clone())));
+        }
+
+        for command in commands {
+            match command {
+                Command::FlyTo { ship, target } => {
+                    let (ship, target) = (entities.entity(ship), entities.entity(target));
+                    if !entities.is_alive(target) {
+                        continue;
+                    }
+
+                    if let Some(behaviour) = behaviour.get_mut(ship) {
+                        *behaviour = ShipBehaviour::FlyTo(target);
+                        if let Some(brain) = brain.get_mut(ship) {
+                            brain.blackboard.sent_to(target);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Record or verify hashes of the simulation state, to detect replays diverging from their recording.
+pub struct HashState;
+
+impl<'a> System<'a> for HashState {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, CurrentTime>,
+        Option<Write<'a, Recording>>,
+        Option<Write<'a, ReplayState>>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Cargo>,
+        ReadStorage<'a, ShipBehaviour>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, recording, replay, pos, vel, cargo, behaviour): Self::SystemData,
+    ) {
+        let record = recording.as_ref().map_or(false, |recording| {
+            recording
+                .hash_interval
+                .map_or(false, |interval| *time.0 % interval == 0)
+        });
+        let verify = replay
+            .as_ref()
+            .map_or(false, |replay| replay.expects_hash(time.0));
+
+        if !record && !verify {
+            return;
+        }
+
+        let hash = replay::state_hash(&entities, &pos, &vel, &cargo, &behaviour);
+
+        if let (true, Some(mut recording)) = (record, recording) {
+            recording.hashes.push((time.0, hash));
+        }
+
+        if let Some(mut replay) = replay {
+            replay.verify(time.0, hash);
+        }
+    }
+}
+
 pub struct IncrementTime;
 
 impl<'a> System<'a> for IncrementTime {
@@ -37,154 +137,86 @@ impl<'a> System<'a> for Movement {
     }
 }
 
-pub struct Rotation;
+pub struct Orbiting;
 
-impl<'a> System<'a> for Rotation {
-    type SystemData = (ReadStorage<'a, AngularMomentum>, WriteStorage<'a, Angle>);
-
-    fn run(&mut self, (momentum, mut angle): Self::SystemData) {
-        for (momentum, angle) in (&momentum, &mut angle).join() {
-            *angle.deref_mut() += *momentum.deref();
-        }
-    }
-}
-
-pub struct DerivePositionalTransform;
-
-impl<'a> System<'a> for DerivePositionalTransform {
+impl<'a> System<'a> for Orbiting {
     type SystemData = (
-        ReadStorage<'a, Position>,
-        ReadStorage<'a, Trader>,
-        ReadStorage<'a, Station>,
-        WriteStorage<'a, Transform>,
+        Entities<'a>,
+        ReadExpect<'a, CurrentTime>,
+        ReadStorage<'a, Orbit>,
+        WriteStorage<'a, Position>,
     );
 
-    fn run(&mut self, (pos, trader, station, mut transform): Self::SystemData) {
-        for (pos, trader, station, transform) in
-            (&pos, (&trader).maybe(), (&station).maybe(), &mut transform).join()
-        {
-            transform.set_scale([1.0, 1.0, 1.0].into());
-
-            // Vary z-level based on type
-            let z = if trader.is_some() {
-                0.2
-            } else if station.is_some() {
-                0.1
-            } else {
-                0.0
-            };
-
-            transform.set_translation_xyz(pos.x.into(), pos.y.into(), z);
-        }
-    }
-}
-
-pub struct DeriveRotationalTransform;
+    fn run(&mut self, (entities, time, orbit, mut pos): Self::SystemData) {
+        // Resolve all positions first, as orbits may be nested.
+        let updated: Vec<(Entity, Point2)> = (&entities, &orbit)
+            .join()
+            .filter_map(|(e, _)| position_at(e, time.0, &orbit, &pos).map(|p| (e, p)))
+            .collect();
 
-impl<'a> System<'a> for DeriveRotationalTransform {
-    type SystemData = (ReadStorage<'a, Angle>, WriteStorage<'a, Transform>);
-
-    fn run(&mut self, (angle, mut transform): Self::SystemData) {
-        for (angle, transform) in (&angle, &mut transform).join() {
-            transform.set_rotation_2d(*angle.deref());
+        for (e, p) in updated {
+            if let Some(pos) = pos.get_mut(e) {
+                *pos = Position::new(p);
+            }
         }
     }
 }
 
-pub struct CameraControl;
+pub struct Fabrication;
 
-impl<'a> System<'a> for CameraControl {
+impl<'a> System<'a> for Fabrication {
     type SystemData = (
-        WriteExpect<'a, CameraState>,
-        ReadExpect<'a, ScreenDimensions>,
-        ReadStorage<'a, Position>,
-        WriteStorage<'a, Camera>,
-        WriteStorage<'a, Transform>,
+        Entities<'a>,
+        WriteStorage<'a, FabricationModule>,
+        WriteStorage<'a, Cargo>,
+        Write<'a, EventChannel<SimEvent>>,
     );
 
-    fn run(&mut self, (camera_state, size, pos, mut camera, mut transform): Self::SystemData) {
-        let zoom = camera_state.zoom;
-
-        for (camera, transform) in (&mut camera, &mut transform).join() {
-            // Update the camera size per zoom level.
-            *camera = Camera::standard_2d(size.width() / zoom, size.height() / zoom);
-
-            match &camera_state.behaviour {
-                CameraBehaviour::Static => (),
-                CameraBehaviour::Follow(target) => {
-                    if let Some(pos) = pos.get(*target) {
-                        transform.set_translation_xyz(pos.x, pos.y, 1.0);
+    fn run(&mut self, (entities, mut module, mut cargo, mut events): Self::SystemData) {
+        for (e, module, cargo) in (&entities, &mut module, &mut cargo).join() {
+            let recipe = module.recipe;
+            match module.progress.as_mut() {
+                None => {
+                    // Only start when all ingredients are available.
+                    let ingredients = recipe
+                        .ingredients
+                        .iter()
+                        .map(|&(t, u)| (t, CargoUnits(-*u)));
+                    if cargo.mass_change_iter(ingredients).is_ok() {
+                        module.progress = Some(Time(0));
+                        events.single_write(SimEvent::FabricationStarted {
+                            station: e,
+                            recipe: recipe.name,
+                        });
                     }
                 }
-                CameraBehaviour::Pan(translation) => {
-                    let factor = f32::powf(2., 1. / zoom);
-                    transform.append_translation_xyz(
-                        translation.x * factor,
-                        translation.y * factor,
-                        0.,
-                    );
+                Some(progress) if *progress < recipe.duration => *progress += Time(1),
+                Some(_) => {
+                    // Hold on to the finished products until there is room for them.
+                    if cargo
+                        .mass_change_iter(recipe.products.iter().cloned())
+                        .is_ok()
+                    {
+                        module.progress = None;
+                        events.single_write(SimEvent::FabricationCompleted {
+                            station: e,
+                            recipe: recipe.name,
+                        });
+                    }
                 }
             }
         }
     }
 }
 
-pub struct UiRelativePositioning;
+pub struct Rotation;
 
-impl<'a> System<'a> for UiRelativePositioning {
-    type SystemData = (
-        ReadExpect<'a, ScreenDimensions>,
-        ReadStorage<'a, Camera>,
-        ReadStorage<'a, Transform>,
-        ReadStorage<'a, Hitbox>,
-        ReadStorage<'a, UiRelative>,
-        WriteStorage<'a, UiTransform>,
-    );
+impl<'a> System<'a> for Rotation {
+    type SystemData = (ReadStorage<'a, AngularMomentum>, WriteStorage<'a, Angle>);
 
-    fn run(
-        &mut self,
-        (size, camera, transform, hitbox, ui_relative, mut ui_transform): Self::SystemData,
-    ) {
-        let size = size.diagonal();
-
-        for (camera, camera_transform) in (&camera, &transform).join() {
-            let projection = camera.projection();
-            for (ui_relative, ui_transform) in (&ui_relative, &mut ui_transform).join() {
-                let UiRelative(parent) = ui_relative;
-                if let Some(parent_transform) = transform.get(*parent) {
-                    let middle_world = (*parent_transform).clone();
-                    let middle_screen = projection.world_to_screen(
-                        math::Point::from(*parent_transform.translation()),
-                        size,
-                        camera_transform,
-                    );
-                    ui_transform.local_x = middle_screen.x;
-                    // TODO fix this inversion.
-                    ui_transform.local_y = size.y - middle_screen.y;
-
-                    if let Some(hitbox) = hitbox.get(*parent) {
-                        let topright_world = middle_world
-                            .clone()
-                            .prepend_translation(math::Vector3::new(
-                                hitbox.x / 2.,
-                                hitbox.y / 2.,
-                                0.,
-                            ))
-                            .clone();
-                        let topright_screen = projection.world_to_screen(
-                            math::Point::from(*topright_world.translation()),
-                            size,
-                            camera_transform,
-                        );
-
-                        let hwidth = topright_screen.x - middle_screen.x;
-                        let hheight = middle_screen.y - topright_screen.y;
-
-                        ui_transform.width = hwidth * 2.;
-                        ui_transform.height = hheight * 2.;
-                    }
-                }
-            }
+    fn run(&mut self, (momentum, mut angle): Self::SystemData) {
+        for (momentum, angle) in (&momentum, &mut angle).join() {
+            *angle.deref_mut() += *momentum.deref();
         }
     }
 }
@@ -0,0 +1,125 @@
+use amethyst::ecs::{
+    join::Join,
+    prelude::{Read, ReadStorage, System, WriteStorage},
+    Entities, Entity,
+};
+use log::warn;
+use rhai::Engine;
+use std::ops::Deref;
+
+use crate::components::*;
+use crate::scripting::{self, Action, Scripts, ShipView, StationView};
+
+/// Let the scripts of idle ships decide where they go.
+///
+/// Ships whose script fails are handed back to the built-in behaviour, rather than failing every tick.
+pub struct RunScripts {
+    engine: Engine,
+}
+
+impl Default for RunScripts {
+    fn default() -> Self {
+        Self {
+            engine: scripting::engine(),
+        }
+    }
+}
+
+impl<'a> System<'a> for RunScripts {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Scripts>,
+        ReadStorage<'a, Sector>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Cargo>,
+        ReadStorage<'a, Station>,
+        ReadStorage<'a, FabricationModule>,
+        WriteStorage<'a, Scripted>,
+        WriteStorage<'a, ShipBehaviour>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, scripts, sector, pos, cargo, station, module, mut scripted, mut behaviour): Self::SystemData,
+    ) {
+        let stations: Vec<(Sector, StationView)> = (
+            &entities,
+            &station,
+            &sector,
+            &pos,
+            (&module).maybe(),
+            (&cargo).maybe(),
+        )
+            .join()
+            .map(|(e, _, &sector, pos, module, cargo)| {
+                let view = StationView {
+                    id: e.id(),
+                    position: *pos.deref(),
+                    recipe: module.map(|module| module.recipe.name),
+                    cargo: cargo.map_or_else(Vec::new, |cargo| cargo.iter().collect()),
+                };
+                (sector, view)
+            })
+            .collect();
+
+        let mut failed: Vec<Entity> = Vec::new();
+
+        for (e, script, &our_sector, our_pos, cargo, behaviour) in (
+            &entities,
+            &scripted,
+            &sector,
+            &pos,
+            (&cargo).maybe(),
+            &mut behaviour,
+        )
+            .join()
+        {
+            if !matches!(behaviour, ShipBehaviour::Idle) {
+                continue;
+            }
+
+            let ast = match scripts.get(&script.0) {
+                Some(ast) => ast,
+                None => {
+                    warn!("Unknown script {:?} for {:?}.", script.0, e);
+                    failed.push(e);
+                    continue;
+                }
+            };
+
+            let ship = ShipView {
+                id: e.id(),
+                sector: our_sector,
+                position: *our_pos.deref(),
+                full: cargo.map_or(true, |cargo| cargo.is_full()),
+                cargo: cargo.map_or_else(Vec::new, |cargo| cargo.iter().collect()),
+                stations: stations
+                    .iter()
+                    .filter(|(sector, _)| *sector == our_sector)
+                    .map(|(_, view)| view.clone())
+                    .collect(),
+            };
+
+            match scripting::decide(&self.engine, ast, &ship) {
+                Ok(Action::Idle) => (),
+                Ok(Action::FlyTo(target)) => {
+                    let target = entities.entity(target);
+                    if entities.is_alive(target) && station.contains(target) {
+                        *behaviour = ShipBehaviour::FlyTo(target);
+                    } else {
+                        warn!("Script {:?} sent {:?} to a non-station.", script.0, e);
+                        failed.push(e);
+                    }
+                }
+                Err(error) => {
+                    warn!("Script {:?} failed for {:?}: {}.", script.0, e, error);
+                    failed.push(e);
+                }
+            }
+        }
+
+        for e in failed {
+            scripted.remove(e);
+        }
+    }
+}
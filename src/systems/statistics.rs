@@ -0,0 +1,85 @@
+use amethyst::{
+    ecs::{
+        join::Join,
+        prelude::{Read, ReadExpect, ReadStorage, System, SystemData, Write},
+        World,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+
+use crate::components::*;
+use crate::events::SimEvent;
+use crate::recipes::Recipes;
+use crate::resources::CurrentTime;
+use crate::statistics::{CargoTotals, Sample, Statistics};
+
+/// Sample the economy into `Statistics` every interval.
+#[derive(Default)]
+pub struct SampleStatistics {
+    reader: Option<ReaderId<SimEvent>>,
+}
+
+impl<'a> System<'a> for SampleStatistics {
+    type SystemData = (
+        ReadExpect<'a, CurrentTime>,
+        Read<'a, EventChannel<SimEvent>>,
+        Read<'a, Recipes>,
+        Write<'a, Statistics>,
+        ReadStorage<'a, Station>,
+        ReadStorage<'a, Cargo>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(
+            world
+                .fetch_mut::<EventChannel<SimEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, (time, events, recipes, mut statistics, station, cargo): Self::SystemData) {
+        let reader = self.reader.as_mut().expect("reader is registered in setup");
+        for event in events.read(reader) {
+            match *event {
+                SimEvent::FabricationStarted { recipe, .. } => {
+                    for &(t, units) in recipes.get(recipe).map_or(&[][..], |r| r.ingredients) {
+                        statistics.consumed[t] = statistics.consumed[t] + units;
+                    }
+                }
+                SimEvent::FabricationCompleted { recipe, .. } => {
+                    for &(t, units) in recipes.get(recipe).map_or(&[][..], |r| r.products) {
+                        statistics.produced[t] = statistics.produced[t] + units;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if *time.0 % statistics.interval() != 0 {
+            return;
+        }
+
+        let mut in_stations = CargoTotals::default();
+        let mut in_transit = CargoTotals::default();
+        for (cargo, station) in (&cargo, (&station).maybe()).join() {
+            let totals = if station.is_some() {
+                &mut in_stations
+            } else {
+                &mut in_transit
+            };
+            for (t, units) in cargo.iter() {
+                totals[t] = totals[t] + units;
+            }
+        }
+
+        let sample = Sample {
+            time: time.0,
+            in_stations,
+            in_transit,
+            produced: std::mem::take(&mut statistics.produced),
+            consumed: std::mem::take(&mut statistics.consumed),
+        };
+        statistics.push(sample);
+    }
+}
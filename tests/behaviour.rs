@@ -0,0 +1,139 @@
+mod common;
+
+use amethyst::ecs::{DispatcherBuilder, Entity, Join, WorldExt};
+use amethyst::shrev::EventChannel;
+
+use common::*;
+use traders::components::*;
+use traders::events::SimEvent;
+use traders::systems::{self, behaviour};
+
+fn fly_to() -> TestWorld {
+    TestWorld::new(
+        DispatcherBuilder::new()
+            .with(behaviour::FlyTo, "behaviour_fly_to", &[])
+            .with(systems::Movement, "movement", &["behaviour_fly_to"]),
+    )
+}
+
+fn behaviour_trees() -> TestWorld {
+    TestWorld::new(
+        DispatcherBuilder::new()
+            .with(behaviour::RunBehaviourTrees, "behaviour_trees", &[])
+            .with(behaviour::FlyTo, "behaviour_fly_to", &["behaviour_trees"])
+            .with(systems::Movement, "movement", &["behaviour_fly_to"]),
+    )
+}
+
+fn spawn(test: &mut TestWorld, class: &str) -> (Entity, Entity) {
+    let station = create_station(&mut test.world, Sector(0), at(300., 100.), None, None, None);
+    let ship = create_ship(
+        &mut test.world,
+        class,
+        Sector(0),
+        at(100., 100.),
+        Faction(0),
+    )
+    .unwrap();
+    send_to(&test.world, ship, station);
+    (ship, station)
+}
+
+#[test]
+fn fly_to_arrives_at_target() {
+    let mut test = fly_to();
+    let (ship, station) = spawn(&mut test, "hauler");
+    let mut reader = test
+        .world
+        .fetch_mut::<EventChannel<SimEvent>>()
+        .register_reader();
+
+    // The station lies 200 units away, covered at one unit per tick.
+    test.run(210);
+
+    let pos = test.get::<Position>(ship).unwrap();
+    assert!((pos.x - 300.).abs() < 0.01 && (pos.y - 100.).abs() < 0.01);
+    assert!(matches!(
+        test.world.read_storage::<ShipBehaviour>().get(ship),
+        Some(ShipBehaviour::Idle)
+    ));
+
+    let vel = test.get::<Velocity>(ship).unwrap();
+    assert_eq!((vel.vector.x, vel.vector.y), (0., 0.));
+
+    let events: Vec<&SimEvent> = test
+        .world
+        .fetch::<EventChannel<SimEvent>>()
+        .read(&mut reader)
+        .collect();
+    assert!(matches!(
+        events.as_slice(),
+        [SimEvent::Arrived { ship: s, target: t }] if *s == ship && *t == station
+    ));
+}
+
+#[test]
+fn fly_to_is_underway_before_arrival() {
+    let mut test = fly_to();
+    let (ship, station) = spawn(&mut test, "hauler");
+
+    test.run(100);
+
+    let pos = test.get::<Position>(ship).unwrap();
+    assert!((pos.x - 200.).abs() < 1.01);
+    assert!(matches!(
+        test.world.read_storage::<ShipBehaviour>().get(ship),
+        Some(&ShipBehaviour::FlyTo(target)) if target == station
+    ));
+}
+
+#[test]
+fn fly_to_moves_at_speed_of_class() {
+    let mut test = fly_to();
+    let (ship, _) = spawn(&mut test, "courier");
+
+    // Couriers cover two units per tick.
+    test.run(50);
+
+    let pos = test.get::<Position>(ship).unwrap();
+    assert!((pos.x - 200.).abs() < 2.01);
+}
+
+#[test]
+fn unknown_class_creates_no_ship() {
+    let mut test = fly_to();
+    let ship = create_ship(
+        &mut test.world,
+        "dreadnought",
+        Sector(0),
+        at(100., 100.),
+        Faction(0),
+    );
+
+    assert!(ship.is_none());
+    assert_eq!(test.world.read_storage::<Trader>().join().count(), 0);
+}
+
+#[test]
+fn behaviour_tree_sends_idle_trader_to_station() {
+    let mut test = behaviour_trees();
+    let station = create_station(&mut test.world, Sector(0), at(300., 100.), None, None, None);
+    let ship = create_ship(
+        &mut test.world,
+        "hauler",
+        Sector(0),
+        at(100., 100.),
+        Faction(0),
+    )
+    .unwrap();
+
+    test.run(1);
+    assert!(matches!(
+        test.world.read_storage::<ShipBehaviour>().get(ship),
+        Some(&ShipBehaviour::FlyTo(target)) if target == station
+    ));
+
+    test.run(210);
+    let pos = test.get::<Position>(ship).unwrap();
+    assert!((pos.x - 300.).abs() < 0.01 && (pos.y - 100.).abs() < 0.01);
+}
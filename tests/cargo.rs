@@ -0,0 +1,110 @@
+mod common;
+
+use amethyst::ecs::DispatcherBuilder;
+
+use common::*;
+use traders::components::*;
+use traders::systems;
+
+#[test]
+fn mass_change_iter_applies_all_changes() {
+    let mut cargo = Cargo::new(CargoUnits(100));
+    cargo
+        .mass_change_iter(
+            vec![
+                (CargoType::Metal, CargoUnits(10)),
+                (CargoType::Carbon, CargoUnits(5)),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+
+    assert_eq!(amount(&cargo, CargoType::Metal), CargoUnits(10));
+    assert_eq!(amount(&cargo, CargoType::Carbon), CargoUnits(5));
+}
+
+#[test]
+fn set_capacity_keeps_cargo_fitting() {
+    let mut cargo = Cargo::new(CargoUnits(100));
+    cargo.change(CargoType::Metal, CargoUnits(40)).unwrap();
+
+    assert!(matches!(
+        cargo.set_capacity(CargoUnits(30)),
+        Err(CargoError::OverCapacity)
+    ));
+    cargo.set_capacity(CargoUnits(40)).unwrap();
+    assert!(cargo.is_full());
+}
+
+#[test]
+fn mass_change_iter_rolls_back_when_insufficient() {
+    let mut cargo = Cargo::new(CargoUnits(100));
+    cargo.change(CargoType::Metal, CargoUnits(10)).unwrap();
+
+    // The first change succeeds on its own, but must be undone as the second one fails.
+    let changes = vec![
+        (CargoType::Metal, CargoUnits(-5)),
+        (CargoType::Carbon, CargoUnits(-1)),
+    ];
+    assert!(matches!(
+        cargo.mass_change_iter(changes.into_iter()),
+        Err(CargoError::Insufficient)
+    ));
+
+    assert_eq!(amount(&cargo, CargoType::Metal), CargoUnits(10));
+    assert_eq!(amount(&cargo, CargoType::Carbon), CargoUnits(0));
+}
+
+#[test]
+fn mass_change_iter_rolls_back_when_over_capacity() {
+    let mut cargo = Cargo::new(CargoUnits(10));
+
+    let changes = vec![
+        (CargoType::Metal, CargoUnits(5)),
+        (CargoType::Carbon, CargoUnits(6)),
+    ];
+    assert!(matches!(
+        cargo.mass_change_iter(changes.into_iter()),
+        Err(CargoError::OverCapacity)
+    ));
+
+    assert_eq!(amount(&cargo, CargoType::Metal), CargoUnits(0));
+    assert_eq!(amount(&cargo, CargoType::Carbon), CargoUnits(0));
+
+    // The rolled back changes no longer take up any room.
+    cargo.change(CargoType::Carbon, CargoUnits(10)).unwrap();
+    assert!(cargo.is_full());
+}
+
+#[test]
+fn fabrication_does_not_start_with_missing_ingredients() {
+    let mut test =
+        TestWorld::new(DispatcherBuilder::new().with(systems::Fabrication, "fabrication", &[]));
+
+    // Fuel takes both water and carbon, of which only water is available.
+    let recipe = recipe_by_name("fuel").unwrap();
+    let station = create_station(
+        &mut test.world,
+        Sector(0),
+        at(100., 100.),
+        None,
+        Some(recipe),
+        None,
+    );
+    test.world
+        .write_storage::<Cargo>()
+        .get_mut(station)
+        .unwrap()
+        .change(CargoType::Water, CargoUnits(5))
+        .unwrap();
+
+    test.run(10);
+
+    let cargo = test.get::<Cargo>(station).unwrap();
+    assert_eq!(amount(&cargo, CargoType::Water), CargoUnits(5));
+    assert!(test
+        .get::<FabricationModule>(station)
+        .unwrap()
+        .progress
+        .is_none());
+}
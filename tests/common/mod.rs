@@ -0,0 +1,65 @@
+//! Utilities shared by the integration tests.
+#![allow(dead_code)]
+
+use amethyst::ecs::{Component, Dispatcher, DispatcherBuilder, Entity, World, WorldExt};
+
+use traders::components::*;
+use traders::resources::*;
+use traders::simulation;
+
+/// Bare world running only a selection of the simulation systems, to test them in isolation.
+pub struct TestWorld {
+    pub world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+}
+
+impl TestWorld {
+    /// Set up a world for the given systems, with all simulation components registered and no entities.
+    pub fn new(systems: DispatcherBuilder<'static, 'static>) -> Self {
+        let mut world = World::new();
+        let mut dispatcher = systems.build();
+        dispatcher.setup(&mut world);
+
+        // The selected systems need not register every component the factories attach.
+        simulation::register_components(&mut world);
+        world.register::<Sector>();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.register::<Obstacle>();
+        world.register::<Angle>();
+        world.register::<AngularMomentum>();
+        world.register::<Orbit>();
+        world.register::<JumpGate>();
+        world.register::<ShipBehaviour>();
+        world.register::<Brain>();
+        world.register::<Cargo>();
+        world.register::<FabricationModule>();
+
+        world.insert(CurrentTime::default());
+        world.insert(SimRng::seed_from_u64(0));
+
+        Self { world, dispatcher }
+    }
+
+    /// Advance the given number of ticks.
+    pub fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            simulation::tick(&mut self.dispatcher, &mut self.world);
+        }
+    }
+
+    pub fn get<T: Component + Clone>(&self, e: Entity) -> Option<T> {
+        self.world.read_storage::<T>().get(e).cloned()
+    }
+}
+
+pub fn at(x: f32, y: f32) -> Position {
+    Position::new(Point2::new(x, y))
+}
+
+pub fn amount(cargo: &Cargo, t: CargoType) -> CargoUnits {
+    cargo
+        .iter()
+        .find(|&(other, _)| other == t)
+        .map_or(CargoUnits(0), |(_, amount)| amount)
+}